@@ -6,12 +6,18 @@ use std::fmt::Display;
 #[derive(Debug)]
 pub enum Error {
     InternalServerError(String),
+    LoggerError(String),
+    ServiceUnavailable(String),
+    ClientError(String),
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Error::InternalServerError(io_error) => write!(f, "{}", io_error),
+            Error::LoggerError(message) => write!(f, "{}", message),
+            Error::ServiceUnavailable(message) => write!(f, "{}", message),
+            Error::ClientError(message) => write!(f, "{}", message),
         }
     }
 }
@@ -1,10 +1,54 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::Mutex;
+
 /// #### BaseStrategy
-/// Base trait for load balancers
-pub trait BaseStrategy<T> {
-    fn init(service_name: String, list: Vec<String>);
+/// Base trait for load balancers, picks the instance a request goes to
+pub trait BaseStrategy<T>: Send + Sync {
+    /// Pick a service instance, or None when the list is empty
+    /// @param instances available instances of the service
+    /// @param key identifies the request, e.g a user ID, for strategies
+    /// routing the same key to the same instance
+    fn choose<'a>(&self, instances: &'a [T], key: Option<&str>) -> Option<&'a T>;
+}
+
+/// Random number generator of the strategies, seeded for reproducible picks
+/// in tests
+pub(crate) struct StrategyRng {
+    rng: Mutex<StdRng>,
+}
+
+impl StrategyRng {
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        StrategyRng {
+            rng: Mutex::new(rng),
+        }
+    }
+
+    /// random index below `len`
+    pub(crate) fn index(&self, len: usize) -> usize {
+        self.rng.lock().unwrap().gen_range(0..len)
+    }
 
-    /// Pick a service instance
-    fn choose() -> T;
+    /// Picks an index with a probability proportional to its weight. Returns
+    /// None when every weight is zero
+    pub(crate) fn weighted(&self, weights: &[f64]) -> Option<usize> {
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
 
-    fn get_instance<K>(name: String, instance_type: K) -> K;
+        let mut point = self.rng.lock().unwrap().gen_range(0.0..total);
+        for (index, weight) in weights.iter().enumerate() {
+            if point < *weight {
+                return Some(index);
+            }
+            point -= weight;
+        }
+        weights.iter().rposition(|weight| *weight > 0.0)
+    }
 }
@@ -0,0 +1,83 @@
+use crate::loadbalancer::base_strategy::{BaseStrategy, StrategyRng};
+use crate::registry::service_instance::ServiceInstance;
+
+/// #### ConsistentHashStrategy
+/// Routes every request key to the same instance, using rendezvous hashing:
+/// the instance scoring the highest hash of key and instance ID wins. When an
+/// instance leaves only its keys move, when one joins it takes a fair share
+/// of the keys from the others.
+///
+/// Requests without key go to a random instance
+pub struct ConsistentHashStrategy {
+    rng: StrategyRng,
+}
+
+seeded_strategy!(ConsistentHashStrategy);
+
+/// FNV-1a, stable across processes and builds unlike the std hasher, so all
+/// replicas of a caller agree on the instance of a key
+fn score(key: &str, instance_id: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in key.bytes().chain([0]).chain(instance_id.bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    // final avalanche, FNV alone spreads similar inputs poorly
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^ (hash >> 33)
+}
+
+impl<T: ServiceInstance + Send + Sync> BaseStrategy<T> for ConsistentHashStrategy {
+    fn choose<'a>(&self, instances: &'a [T], key: Option<&str>) -> Option<&'a T> {
+        if instances.is_empty() {
+            return None;
+        }
+        match key {
+            Some(key) => instances
+                .iter()
+                .max_by_key(|instance| score(key, &instance.get_instance_id())),
+            None => instances.get(self.rng.index(instances.len())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::default_service_instance::DefaultServiceInstance;
+    use crate::registry::service_instance::ServiceInstanceOptions;
+
+    fn instance(id: &str) -> DefaultServiceInstance {
+        DefaultServiceInstance::new(ServiceInstanceOptions {
+            instance_id: id.to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn it_only_moves_the_keys_of_a_removed_instance() {
+        let strategy = ConsistentHashStrategy::new();
+        let all = [instance("a"), instance("b"), instance("c")];
+        let without_b = [instance("a"), instance("c")];
+
+        let mut kept = 0;
+        for user in 0..300 {
+            let key = format!("user-{user}");
+            let before = strategy.choose(&all, Some(&key)).unwrap().get_instance_id();
+            let after = strategy
+                .choose(&without_b, Some(&key))
+                .unwrap()
+                .get_instance_id();
+
+            if before == "b" {
+                assert_ne!(after, "b");
+            } else {
+                assert_eq!(before, after);
+                kept += 1;
+            }
+        }
+        // every instance gets a fair share of the keys
+        assert!((150..250).contains(&kept));
+    }
+}
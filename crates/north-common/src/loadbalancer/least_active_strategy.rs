@@ -0,0 +1,62 @@
+use crate::loadbalancer::base_strategy::{BaseStrategy, StrategyRng};
+use crate::registry::service_instance::ServiceInstance;
+
+/// #### LeastActiveStrategy
+/// Picks the instance with the fewest active requests, according to its
+/// [ServiceInstanceState](crate::registry::service_instance_state::ServiceInstanceState).
+/// Ties are broken at random
+pub struct LeastActiveStrategy {
+    rng: StrategyRng,
+}
+
+seeded_strategy!(LeastActiveStrategy);
+
+impl<T: ServiceInstance + Clone + Send + Sync> BaseStrategy<T> for LeastActiveStrategy {
+    fn choose<'a>(&self, instances: &'a [T], _key: Option<&str>) -> Option<&'a T> {
+        let active: Vec<i64> = instances
+            .iter()
+            .map(|instance| instance.clone().get_state().get_active_requests_count(None))
+            .collect();
+        let least = active.iter().min()?;
+
+        let candidates: Vec<&T> = instances
+            .iter()
+            .zip(active.iter())
+            .filter(|(_, count)| *count == least)
+            .map(|(instance, _)| instance)
+            .collect();
+        Some(candidates[self.rng.index(candidates.len())])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::default_service_instance::DefaultServiceInstance;
+    use crate::registry::service_instance::ServiceInstanceOptions;
+    use crate::registry::service_instance_state::ServiceInstanceState;
+
+    fn instance(id: &str, active: usize) -> DefaultServiceInstance {
+        let state = ServiceInstanceState::new(None);
+        state.set_active_requests_count_timeout(60);
+        for _ in 0..active {
+            state.increment_active_requests();
+        }
+        DefaultServiceInstance::new(ServiceInstanceOptions {
+            instance_id: id.to_string(),
+            state: Some(state),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn it_picks_the_least_busy_instance() {
+        let strategy = LeastActiveStrategy::with_seed(1);
+        let instances = [instance("a", 3), instance("b", 1), instance("c", 2)];
+
+        for _ in 0..5 {
+            let instance = strategy.choose(&instances, None).unwrap();
+            assert_eq!(instance.get_instance_id(), "b");
+        }
+    }
+}
@@ -1,2 +1,35 @@
+/// Implements `Default`, `new` and `with_seed` for a strategy keeping its
+/// random number generator in an `rng` field, so every strategy seeds alike
+macro_rules! seeded_strategy {
+    ($strategy:ident) => {
+        impl Default for $strategy {
+            fn default() -> Self {
+                $strategy {
+                    rng: $crate::loadbalancer::base_strategy::StrategyRng::new(None),
+                }
+            }
+        }
+
+        impl $strategy {
+            pub fn new() -> Self {
+                Self::default()
+            }
+
+            /// Same seed, same sequence of random picks
+            pub fn with_seed(seed: u64) -> Self {
+                $strategy {
+                    rng: $crate::loadbalancer::base_strategy::StrategyRng::new(Some(seed)),
+                }
+            }
+        }
+    };
+}
+
 pub mod base_strategy;
+pub mod consistent_hash_strategy;
+pub mod least_active_strategy;
+pub mod random_strategy;
+pub mod response_time_strategy;
+pub mod round_robin_strategy;
 pub mod service_pool;
+pub mod weighted_strategy;
@@ -0,0 +1,38 @@
+use crate::loadbalancer::base_strategy::{BaseStrategy, StrategyRng};
+
+/// #### RandomStrategy
+/// Picks an instance at random
+pub struct RandomStrategy {
+    rng: StrategyRng,
+}
+
+seeded_strategy!(RandomStrategy);
+
+impl<T> BaseStrategy<T> for RandomStrategy {
+    fn choose<'a>(&self, instances: &'a [T], _key: Option<&str>) -> Option<&'a T> {
+        if instances.is_empty() {
+            return None;
+        }
+        instances.get(self.rng.index(instances.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_repeats_picks_with_the_same_seed() {
+        let instances = ["a", "b", "c", "d"];
+        let picks = |strategy: RandomStrategy| -> Vec<&str> {
+            (0..20)
+                .map(|_| *strategy.choose(&instances, None).unwrap())
+                .collect()
+        };
+
+        assert_eq!(
+            picks(RandomStrategy::with_seed(7)),
+            picks(RandomStrategy::with_seed(7))
+        );
+    }
+}
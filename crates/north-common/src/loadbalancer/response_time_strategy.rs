@@ -0,0 +1,63 @@
+use crate::loadbalancer::base_strategy::{BaseStrategy, StrategyRng};
+use crate::registry::service_instance::ServiceInstance;
+
+/// #### ResponseTimeStrategy
+/// Picks an instance at random with a probability inversely proportional to
+/// its average response time, so faster instances get more traffic while
+/// slower ones keep getting some and can recover
+pub struct ResponseTimeStrategy {
+    rng: StrategyRng,
+}
+
+seeded_strategy!(ResponseTimeStrategy);
+
+impl<T: ServiceInstance + Clone + Send + Sync> BaseStrategy<T> for ResponseTimeStrategy {
+    fn choose<'a>(&self, instances: &'a [T], _key: Option<&str>) -> Option<&'a T> {
+        // instances without samples average 0ms and get tried first
+        let weights: Vec<f64> = instances
+            .iter()
+            .map(|instance| {
+                let average = instance.clone().get_state().get_response_time_avg().max(0);
+                1.0 / (average as f64 + 1.0)
+            })
+            .collect();
+        self.rng
+            .weighted(&weights)
+            .and_then(|index| instances.get(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::default_service_instance::DefaultServiceInstance;
+    use crate::registry::service_instance::ServiceInstanceOptions;
+    use crate::registry::service_instance_state::ServiceInstanceState;
+    use std::collections::HashMap;
+
+    fn instance(id: &str, response_time: i64) -> DefaultServiceInstance {
+        let state = ServiceInstanceState::new(None);
+        state.increment_request_counts();
+        state.set_response_time(response_time);
+        DefaultServiceInstance::new(ServiceInstanceOptions {
+            instance_id: id.to_string(),
+            state: Some(state),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn it_favours_fast_instances() {
+        let strategy = ResponseTimeStrategy::with_seed(3);
+        let instances = [instance("fast", 9), instance("slow", 99)];
+
+        let mut picks = HashMap::new();
+        for _ in 0..1000 {
+            let instance = strategy.choose(&instances, None).unwrap();
+            *picks.entry(instance.get_instance_id()).or_insert(0) += 1;
+        }
+
+        assert!(picks["fast"] > 5 * picks["slow"]);
+        assert!(picks["slow"] > 0);
+    }
+}
@@ -0,0 +1,43 @@
+use crate::loadbalancer::base_strategy::BaseStrategy;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// #### RoundRobinStrategy
+/// Picks the instances in turn
+#[derive(Default)]
+pub struct RoundRobinStrategy {
+    next: AtomicUsize,
+}
+
+impl RoundRobinStrategy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T> BaseStrategy<T> for RoundRobinStrategy {
+    fn choose<'a>(&self, instances: &'a [T], _key: Option<&str>) -> Option<&'a T> {
+        if instances.is_empty() {
+            return None;
+        }
+        let next = self.next.fetch_add(1, Ordering::Relaxed);
+        instances.get(next % instances.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_picks_instances_in_turn() {
+        let strategy = RoundRobinStrategy::new();
+        let instances = ["a", "b", "c"];
+
+        let picks: Vec<&str> = (0..4)
+            .map(|_| *strategy.choose(&instances, None).unwrap())
+            .collect();
+
+        assert_eq!(picks, ["a", "b", "c", "a"]);
+        assert!(strategy.choose(&[] as &[&str], None).is_none());
+    }
+}
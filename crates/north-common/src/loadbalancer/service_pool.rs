@@ -1,9 +1,422 @@
-use crate::registry::service_instance::ServiceInstance;
-use std::sync::Arc;
+use crate::discovery::discovery_client::DiscoveryClient;
+use crate::error::Error;
+use crate::registry::service_instance::PooledServiceInstance;
+use crate::registry::service_instance_state::{RequestGuard, ServiceInstanceState};
+use chrono::Local;
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+/// #### ServicePoolOptions
+/// Refresh and outlier ejection of a [ServicePool]
+#[derive(Clone, Debug)]
+pub struct ServicePoolOptions {
+    /// Interval between two discovery queries when polling
+    ///
+    /// @defaults to 30 seconds
+    pub refresh_interval: Duration,
+
+    /// Failures in a row after which an instance is ejected
+    ///
+    /// @defaults to 5
+    pub ejection_threshold: i64,
+
+    /// Time an ejected instance stays out of the pool
+    ///
+    /// @defaults to 30 seconds
+    pub ejection_cool_off: Duration,
+
+    /// Share of the instances that can be ejected at the same time, so a
+    /// failing dependency of every instance doesn't empty the pool
+    ///
+    /// @defaults to 50 percent
+    pub max_ejection_percent: u8,
+}
+
+impl Default for ServicePoolOptions {
+    fn default() -> Self {
+        ServicePoolOptions {
+            refresh_interval: Duration::from_secs(30),
+            ejection_threshold: 5,
+            ejection_cool_off: Duration::from_secs(30),
+            max_ejection_percent: 50,
+        }
+    }
+}
+
+/// State of an instance in a [PoolSnapshot]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct InstanceSnapshot {
+    pub instance_id: String,
+    pub uri: String,
+    pub healthy: bool,
+    pub ejected: bool,
+    pub active_requests: i64,
+    pub total_requests: i64,
+    pub failure_counts: i64,
+    pub response_time_avg: i64,
+    pub response_time_max: i64,
+}
+
+/// Point in time view of a [ServicePool], e.g for metrics
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct PoolSnapshot {
+    pub service: String,
+
+    /// last refresh timestamp in milliseconds
+    pub last_refresh: Option<i64>,
+    pub instances: Vec<InstanceSnapshot>,
+}
+
+struct Member<T> {
+    instance: T,
+
+    /// shared with the instance, kept across refreshes
+    state: ServiceInstanceState,
+    ejected_until: Option<Instant>,
+}
+
+impl<T> Member<T> {
+    fn is_ejected(&self) -> bool {
+        self.ejected_until.is_some()
+    }
+
+    /// Readmits the instance once its cool-off is over, with a clean slate
+    fn readmit(&mut self, now: Instant) {
+        if matches!(self.ejected_until, Some(until) if until <= now) {
+            self.ejected_until = None;
+            self.state.reset_consecutive_failures();
+        }
+    }
+}
+
+struct PoolInner<T> {
+    service: String,
+    options: ServicePoolOptions,
+    members: Mutex<Vec<Member<T>>>,
+    last_refresh: Mutex<Option<i64>>,
+}
 
 /// #### ServicePool
-#[allow(dead_code)]
-pub struct ServicePool {
-    /// services in the pool
-    _services: Vec<Arc<dyn ServiceInstance>>,
+/// Instances of a service kept current from a [DiscoveryClient], along with
+/// their request stats. Unhealthy instances are left out, and instances
+/// failing several requests in a row are ejected for a while.
+///
+/// Clones share the same pool
+pub struct ServicePool<T> {
+    inner: Arc<PoolInner<T>>,
+}
+
+impl<T> Clone for ServicePool<T> {
+    fn clone(&self) -> Self {
+        ServicePool {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> ServicePool<T>
+where
+    T: PooledServiceInstance + Clone + Send + Sync + 'static,
+{
+    pub fn new(service: &str, options: ServicePoolOptions) -> Self {
+        ServicePool {
+            inner: Arc::new(PoolInner {
+                service: service.to_string(),
+                options,
+                members: Mutex::new(vec![]),
+                last_refresh: Mutex::new(None),
+            }),
+        }
+    }
+
+    pub fn service(&self) -> &str {
+        &self.inner.service
+    }
+
+    /// Replaces the instances of the pool. Stats and ejections of the
+    /// instances still present are kept, the new instance objects share the
+    /// state of the previous ones
+    pub fn update(&self, instances: Vec<T>) {
+        let mut members = self.inner.members.lock().unwrap();
+        let mut previous = std::mem::take(&mut *members);
+
+        for mut instance in instances {
+            let id = instance.get_instance_id();
+            let member = match previous
+                .iter()
+                .position(|m| m.instance.get_instance_id() == id)
+            {
+                Some(index) => {
+                    let mut member = previous.swap_remove(index);
+                    instance.set_state(member.state.clone());
+                    member.instance = instance;
+                    member
+                }
+                None => Member {
+                    state: instance.clone().get_state(),
+                    instance,
+                    ejected_until: None,
+                },
+            };
+            member.state.set_status(member.instance.get_status().into());
+            members.push(member);
+        }
+        *self.inner.last_refresh.lock().unwrap() = Some(Local::now().timestamp_millis());
+    }
+
+    /// Queries the instances once
+    pub async fn refresh<D: DiscoveryClient<T>>(&self, discovery: D) -> Result<(), Error> {
+        let instances = discovery.get_instances(self.inner.service.clone()).await?;
+        self.update(instances);
+        Ok(())
+    }
+
+    /// Queries the instances every `refresh_interval` until the pool is
+    /// dropped. Failed queries are logged and the current instances kept
+    pub fn spawn_polling<D>(&self, discovery: D) -> JoinHandle<()>
+    where
+        D: DiscoveryClient<T> + Clone + Send + Sync + 'static,
+    {
+        let pool = Arc::downgrade(&self.inner);
+        let interval = self.inner.options.refresh_interval;
+        tokio::spawn(async move {
+            loop {
+                let pool = match upgrade(&pool) {
+                    Some(pool) => pool,
+                    None => return,
+                };
+                if let Err(e) = pool.refresh(discovery.clone()).await {
+                    log::warn!("cannot refresh instances of {}: {e}", pool.service());
+                }
+                drop(pool);
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    /// Applies every instance list of `updates`, e.g from a discovery watch,
+    /// until the stream ends or the pool is dropped
+    pub fn spawn_watch<S>(&self, updates: S) -> JoinHandle<()>
+    where
+        S: Stream<Item = Result<Vec<T>, Error>> + Send + 'static,
+    {
+        let pool = Arc::downgrade(&self.inner);
+        tokio::spawn(async move {
+            futures::pin_mut!(updates);
+            while let Some(update) = updates.next().await {
+                let pool = match upgrade(&pool) {
+                    Some(pool) => pool,
+                    None => return,
+                };
+                match update {
+                    Ok(instances) => pool.update(instances),
+                    Err(e) => log::warn!("cannot watch instances of {}: {e}", pool.service()),
+                }
+            }
+        })
+    }
+
+    /// Healthy instances that aren't ejected
+    pub fn instances(&self) -> Vec<T> {
+        let mut members = self.inner.members.lock().unwrap();
+        self.eject(&mut members);
+        members
+            .iter()
+            .filter(|member| member.state.is_healthy() && !member.is_ejected())
+            .map(|member| member.instance.clone())
+            .collect()
+    }
+
+    /// Readmits the instances whose cool-off is over, then ejects the ones
+    /// that failed `ejection_threshold` requests in a row
+    fn eject(&self, members: &mut [Member<T>]) {
+        let options = &self.inner.options;
+        let now = Instant::now();
+        members.iter_mut().for_each(|member| member.readmit(now));
+
+        let max_ejected = members.len() * options.max_ejection_percent as usize / 100;
+        let mut ejected = members.iter().filter(|member| member.is_ejected()).count();
+        for member in members.iter_mut() {
+            let failures = member.state.get_consecutive_failures();
+            if member.is_ejected() || failures < options.ejection_threshold {
+                continue;
+            }
+            let instance_id = member.instance.get_instance_id();
+            if ejected >= max_ejected {
+                log::warn!(
+                    "{instance_id} of {} keeps failing, but {ejected} instances are already ejected",
+                    self.inner.service
+                );
+                continue;
+            }
+            log::warn!(
+                "ejecting {instance_id} of {} for {:?} after {failures} failures in a row",
+                self.inner.service,
+                options.ejection_cool_off,
+            );
+            member.ejected_until = Some(now + options.ejection_cool_off);
+            ejected += 1;
+        }
+    }
+
+    /// Request stats of an instance
+    pub fn state(&self, instance_id: &str) -> Option<ServiceInstanceState> {
+        let members = self.inner.members.lock().unwrap();
+        members
+            .iter()
+            .find(|member| member.instance.get_instance_id() == instance_id)
+            .map(|member| member.state.clone())
+    }
+
+    /// Records a request to an instance until the guard drops, see
+    /// [ServiceInstanceState::acquire]
+    pub fn acquire(&self, instance_id: &str) -> Option<RequestGuard> {
+        self.state(instance_id).map(|state| state.acquire())
+    }
+
+    pub fn snapshot(&self) -> PoolSnapshot {
+        let mut members = self.inner.members.lock().unwrap();
+        self.eject(&mut members);
+        let instances = members
+            .iter()
+            .map(|member| InstanceSnapshot {
+                instance_id: member.instance.get_instance_id(),
+                uri: member.instance.get_uri(),
+                healthy: member.state.is_healthy(),
+                ejected: member.is_ejected(),
+                active_requests: member.state.get_active_requests_count(None),
+                total_requests: member.state.get_total_requests(),
+                failure_counts: member.state.get_failure_counts(),
+                response_time_avg: member.state.get_response_time_avg(),
+                response_time_max: member.state.get_response_time_max(),
+            })
+            .collect();
+
+        PoolSnapshot {
+            service: self.inner.service.clone(),
+            last_refresh: *self.inner.last_refresh.lock().unwrap(),
+            instances,
+        }
+    }
+}
+
+fn upgrade<T>(pool: &Weak<PoolInner<T>>) -> Option<ServicePool<T>> {
+    pool.upgrade().map(|inner| ServicePool { inner })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::default_service_instance::DefaultServiceInstance;
+    use crate::registry::service_instance::{ServiceInstance, ServiceInstanceOptions};
+    use async_trait::async_trait;
+
+    fn instance(id: &str, status: &str) -> DefaultServiceInstance {
+        DefaultServiceInstance::new(ServiceInstanceOptions {
+            instance_id: id.to_string(),
+            service_id: "orders-service".to_string(),
+            status: status.to_string().into(),
+            ..Default::default()
+        })
+    }
+
+    fn ids(instances: Vec<DefaultServiceInstance>) -> Vec<String> {
+        instances.iter().map(|i| i.get_instance_id()).collect()
+    }
+
+    #[derive(Clone)]
+    struct Discovery(Arc<Mutex<Vec<DefaultServiceInstance>>>);
+
+    #[async_trait]
+    impl DiscoveryClient<DefaultServiceInstance> for Discovery {
+        fn description(self) -> String {
+            "test".into()
+        }
+
+        async fn get_instances(self, _: String) -> Result<Vec<DefaultServiceInstance>, Error> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+
+        async fn get_all_instances(self) -> Result<Vec<DefaultServiceInstance>, Error> {
+            Ok(self.0.lock().unwrap().clone())
+        }
+
+        async fn get_services(self) -> Result<Vec<String>, Error> {
+            Ok(vec!["orders-service".to_string()])
+        }
+    }
+
+    #[test]
+    fn it_leaves_out_unhealthy_instances_and_keeps_stats() {
+        let pool = ServicePool::new("orders-service", ServicePoolOptions::default());
+        pool.update(vec![instance("a", "passing"), instance("b", "critical")]);
+        drop(pool.acquire("a"));
+
+        pool.update(vec![instance("a", "warning"), instance("c", "passing")]);
+
+        let instances = pool.instances();
+        assert_eq!(ids(instances.clone()), ["a", "c"]);
+        assert_eq!(instances[0].clone().get_state().get_total_requests(), 1);
+        assert!(pool.state("b").is_none());
+    }
+
+    #[test]
+    fn it_ejects_failing_instances_until_the_cool_off_ends() {
+        let pool = ServicePool::new(
+            "orders-service",
+            ServicePoolOptions {
+                ejection_threshold: 2,
+                ejection_cool_off: Duration::from_millis(20),
+                ..ServicePoolOptions::default()
+            },
+        );
+        pool.update(vec![instance("a", "passing"), instance("b", "passing")]);
+
+        for _ in 0..2 {
+            pool.acquire("a").unwrap().fail();
+        }
+        assert_eq!(ids(pool.instances()), ["b"]);
+        assert!(pool.snapshot().instances[0].ejected);
+
+        // at most half of the pool is ejected
+        for _ in 0..2 {
+            pool.acquire("b").unwrap().fail();
+        }
+        assert_eq!(ids(pool.instances()), ["b"]);
+
+        // a is readmitted, which leaves room to eject b
+        std::thread::sleep(Duration::from_millis(25));
+        assert_eq!(ids(pool.instances()), ["a"]);
+        assert_eq!(pool.snapshot().instances[0].failure_counts, 2);
+    }
+
+    #[tokio::test]
+    async fn it_polls_the_discovery_client() {
+        let registered = Arc::new(Mutex::new(vec![instance("a", "passing")]));
+        let pool = ServicePool::new(
+            "orders-service",
+            ServicePoolOptions {
+                refresh_interval: Duration::from_millis(10),
+                ..ServicePoolOptions::default()
+            },
+        );
+
+        let polling = pool.spawn_polling(Discovery(registered.clone()));
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(ids(pool.instances()), ["a"]);
+
+        registered.lock().unwrap().push(instance("b", "passing"));
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(ids(pool.instances()), ["a", "b"]);
+        assert!(pool.snapshot().last_refresh.is_some());
+
+        drop(pool);
+        tokio::time::timeout(Duration::from_secs(1), polling)
+            .await
+            .unwrap()
+            .unwrap();
+    }
 }
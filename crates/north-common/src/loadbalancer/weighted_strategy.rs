@@ -0,0 +1,71 @@
+use crate::loadbalancer::base_strategy::{BaseStrategy, StrategyRng};
+use crate::registry::service_instance::ServiceInstance;
+
+/// Metadata key holding the weight of an instance. Consul discovery fills it
+/// from the `Weights` of the service, matching the health of the instance
+pub const WEIGHT_METADATA_KEY: &str = "weight";
+
+/// #### WeightedStrategy
+/// Picks an instance at random with a probability proportional to its
+/// weight. Instances without weight count as 1, instances weighing 0 get no
+/// traffic
+pub struct WeightedStrategy {
+    rng: StrategyRng,
+}
+
+seeded_strategy!(WeightedStrategy);
+
+/// Weight of an instance read from its metadata
+pub fn instance_weight(instance: &impl ServiceInstance) -> u32 {
+    instance
+        .get_metadata()
+        .get(WEIGHT_METADATA_KEY)
+        .and_then(|weight| weight.parse().ok())
+        .unwrap_or(1)
+}
+
+impl<T: ServiceInstance + Send + Sync> BaseStrategy<T> for WeightedStrategy {
+    fn choose<'a>(&self, instances: &'a [T], _key: Option<&str>) -> Option<&'a T> {
+        let weights: Vec<f64> = instances
+            .iter()
+            .map(|instance| instance_weight(instance) as f64)
+            .collect();
+        self.rng
+            .weighted(&weights)
+            .and_then(|index| instances.get(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::default_service_instance::DefaultServiceInstance;
+    use crate::registry::service_instance::ServiceInstanceOptions;
+    use std::collections::HashMap;
+
+    fn instance(id: &str, weight: &str) -> DefaultServiceInstance {
+        DefaultServiceInstance::new(ServiceInstanceOptions {
+            instance_id: id.to_string(),
+            metadata: Some(HashMap::from([(
+                WEIGHT_METADATA_KEY.to_string(),
+                weight.to_string(),
+            )])),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn it_spreads_picks_by_weight() {
+        let strategy = WeightedStrategy::with_seed(42);
+        let instances = [instance("a", "3"), instance("b", "1"), instance("c", "0")];
+
+        let mut picks = HashMap::new();
+        for _ in 0..1000 {
+            let instance = strategy.choose(&instances, None).unwrap();
+            *picks.entry(instance.get_instance_id()).or_insert(0) += 1;
+        }
+
+        assert!(picks["a"] > 2 * picks["b"]);
+        assert!(!picks.contains_key("c"));
+    }
+}
@@ -1,4 +1,6 @@
-use crate::registry::service_instance::{ServiceInstance, ServiceInstanceOptions};
+use crate::registry::service_instance::{
+    PooledServiceInstance, ServiceInstance, ServiceInstanceOptions,
+};
 use crate::registry::service_instance_state::ServiceInstanceState;
 use std::collections::HashMap;
 
@@ -93,6 +95,13 @@ impl ServiceInstance for DefaultServiceInstance {
     }
 }
 
+impl PooledServiceInstance for DefaultServiceInstance {
+    fn set_state(&mut self, state: ServiceInstanceState) {
+        self.options.state = Some(state.clone());
+        self.state = state;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::registry::default_service_instance::DefaultServiceInstance;
@@ -91,3 +91,12 @@ pub trait ServiceInstance {
     /// Returns service instance state
     fn get_state(self) -> ServiceInstanceState;
 }
+
+/// #### PooledServiceInstance
+/// Service instance kept in a
+/// [ServicePool](crate::loadbalancer::service_pool::ServicePool), whose
+/// request stats carry over to the new object discovery returns for it
+pub trait PooledServiceInstance: ServiceInstance {
+    /// Shares the state of another object of the same instance
+    fn set_state(&mut self, state: ServiceInstanceState);
+}
@@ -1,150 +1,398 @@
 use crate::registry::service_status_constants::ServiceStatus;
 use chrono::Local;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-/// [ServiceInstanceState] contains state of every service node
+/// Active requests count timeout in seconds used unless set
+const DEFAULT_ACTIVE_REQUESTS_COUNT_TIMEOUT: i64 = 60;
+
+/// [ServiceInstanceState] contains state of every service node.
+///
+/// Counters are atomics shared by every clone, so concurrent requests to an
+/// instance update the same stats without locking
 #[derive(Clone)]
 pub struct ServiceInstanceState {
+    inner: Arc<StateInner>,
+}
+
+struct StateInner {
     /// service status
-    status: ServiceStatus,
+    status: AtomicU8,
 
     /// total api request count
-    total_requests: i64,
+    total_requests: AtomicI64,
 
     /// total active request count
-    active_requests_count: i64,
+    active_requests_count: AtomicI64,
 
     /// weight of the service
-    weight: i64,
+    weight: AtomicI64,
 
-    /// API avg response time is milliseconds
-    response_time_avg: i64,
+    /// sum of the API response times in milliseconds
+    response_time_total: AtomicI64,
+
+    /// count of the API response times recorded
+    response_time_samples: AtomicI64,
 
     /// API max response time is milliseconds
-    response_time_max: i64,
+    response_time_max: AtomicI64,
 
-    /// active request count timeout in milliseconds
-    active_requests_count_timeout: i16,
+    /// active request count timeout in seconds
+    active_requests_count_timeout: AtomicI64,
 
     /// last active requests count change timestamp in milliseconds
-    last_active_requests_count_change_timestamp: i64,
+    last_active_requests_count_change_timestamp: AtomicI64,
 
     /// first connection timestamp in milliseconds
-    first_connection_timestamp: i64,
+    first_connection_timestamp: AtomicI64,
 
-    /// last connection failed timestamp in milliseconds
-    last_connection_failed_timestamp: Option<i64>,
+    /// last connection failed timestamp in milliseconds, 0 when none failed
+    last_connection_failed_timestamp: AtomicI64,
 
     /// last connection failed message
-    last_connection_failed_message: String,
+    last_connection_failed_message: Mutex<String>,
 
     /// count of requests failures
-    failure_counts: i64,
+    failure_counts: AtomicI64,
+
+    /// count of requests failures since the last success
+    consecutive_failures: AtomicI64,
 
     /// fixed weight
-    fixed_weight: bool,
+    fixed_weight: AtomicBool,
+}
+
+fn status_to_u8(status: &ServiceStatus) -> u8 {
+    match status {
+        ServiceStatus::Critical => 0,
+        ServiceStatus::Passing => 1,
+        ServiceStatus::Warning => 2,
+    }
+}
+
+fn status_from_u8(status: u8) -> ServiceStatus {
+    match status {
+        1 => ServiceStatus::Passing,
+        2 => ServiceStatus::Warning,
+        _ => ServiceStatus::Critical,
+    }
 }
 
 impl Default for ServiceInstanceState {
     fn default() -> Self {
-        ServiceInstanceState {
-            status: Default::default(),
-            total_requests: 0,
-            active_requests_count: 0,
-            weight: 0,
-            response_time_avg: 0,
-            response_time_max: 0,
-            active_requests_count_timeout: 0,
-            last_active_requests_count_change_timestamp: 0,
-            first_connection_timestamp: 0,
-            last_connection_failed_timestamp: None,
-            last_connection_failed_message: "".to_string(),
-            failure_counts: 0,
-            fixed_weight: false,
-        }
+        ServiceInstanceState::new(None)
     }
 }
 
 impl ServiceInstanceState {
     pub fn new(fixed_weight: Option<bool>) -> Self {
         ServiceInstanceState {
-            status: Default::default(),
-            total_requests: 0,
-            active_requests_count: 0,
-            weight: 0,
-            response_time_avg: 0,
-            response_time_max: 0,
-            active_requests_count_timeout: 0,
-            last_active_requests_count_change_timestamp: 0,
-            first_connection_timestamp: 0,
-            last_connection_failed_timestamp: None,
-            last_connection_failed_message: "".to_string(),
-            failure_counts: 0,
-            fixed_weight: fixed_weight.unwrap_or(false),
+            inner: Arc::new(StateInner {
+                status: AtomicU8::new(status_to_u8(&ServiceStatus::default())),
+                total_requests: AtomicI64::new(0),
+                active_requests_count: AtomicI64::new(0),
+                weight: AtomicI64::new(0),
+                response_time_total: AtomicI64::new(0),
+                response_time_samples: AtomicI64::new(0),
+                response_time_max: AtomicI64::new(0),
+                active_requests_count_timeout: AtomicI64::new(
+                    DEFAULT_ACTIVE_REQUESTS_COUNT_TIMEOUT,
+                ),
+                last_active_requests_count_change_timestamp: AtomicI64::new(0),
+                first_connection_timestamp: AtomicI64::new(0),
+                last_connection_failed_timestamp: AtomicI64::new(0),
+                last_connection_failed_message: Mutex::new("".to_string()),
+                failure_counts: AtomicI64::new(0),
+                consecutive_failures: AtomicI64::new(0),
+                fixed_weight: AtomicBool::new(fixed_weight.unwrap_or(false)),
+            }),
         }
     }
 
-    pub fn get_active_requests_count(&mut self, current_time: Option<i64>) -> i64 {
-        let mut _current_time = current_time.unwrap_or_else(|| Local::now().timestamp_millis());
-        let count = self.active_requests_count;
+    /// Active requests, or 0 once the count didn't change for the active
+    /// requests count timeout, e.g because requests were never recorded as
+    /// finished
+    pub fn get_active_requests_count(&self, current_time: Option<i64>) -> i64 {
+        let current_time = current_time.unwrap_or_else(|| Local::now().timestamp_millis());
+        let inner = &self.inner;
+        let count = inner.active_requests_count.load(Ordering::Acquire);
+        let last_change = inner
+            .last_active_requests_count_change_timestamp
+            .load(Ordering::Acquire);
+        let timeout = inner.active_requests_count_timeout.load(Ordering::Relaxed) * 1000;
+
         if count == 0 {
             0
-        } else if _current_time - self.last_active_requests_count_change_timestamp
-            > (self.active_requests_count_timeout * 1000) as i64
-            || count < 0
-        {
-            self.active_requests_count = 0;
-            self.active_requests_count
+        } else if current_time - last_change > timeout || count < 0 {
+            // a request starting meanwhile keeps its count
+            let _ = inner.active_requests_count.compare_exchange(
+                count,
+                0,
+                Ordering::AcqRel,
+                Ordering::Relaxed,
+            );
+            0
         } else {
             count
         }
     }
 
+    /// Active requests are considered stale, and reset, once the count
+    /// didn't change for `seconds`
+    pub fn set_active_requests_count_timeout(&self, seconds: i16) {
+        self.inner
+            .active_requests_count_timeout
+            .store(seconds as i64, Ordering::Relaxed);
+    }
+
+    pub fn get_response_time_avg(&self) -> i64 {
+        let samples = self.inner.response_time_samples.load(Ordering::Acquire);
+        match samples {
+            0 => 0,
+            _ => self.inner.response_time_total.load(Ordering::Acquire) / samples,
+        }
+    }
+
+    pub fn get_response_time_max(&self) -> i64 {
+        self.inner.response_time_max.load(Ordering::Relaxed)
+    }
+
+    pub fn get_total_requests(&self) -> i64 {
+        self.inner.total_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn get_failure_counts(&self) -> i64 {
+        self.inner.failure_counts.load(Ordering::Relaxed)
+    }
+
+    /// Failures since the last success
+    pub fn get_consecutive_failures(&self) -> i64 {
+        self.inner.consecutive_failures.load(Ordering::Relaxed)
+    }
+
+    pub fn reset_consecutive_failures(&self) {
+        self.inner.consecutive_failures.store(0, Ordering::Relaxed);
+    }
+
+    pub fn get_weight(&self) -> i64 {
+        self.inner.weight.load(Ordering::Relaxed)
+    }
+
+    pub fn get_status(&self) -> ServiceStatus {
+        status_from_u8(self.inner.status.load(Ordering::Relaxed))
+    }
+
+    pub fn set_status(&self, status: ServiceStatus) {
+        self.inner
+            .status
+            .store(status_to_u8(&status), Ordering::Relaxed);
+    }
+
     pub fn is_healthy(&self) -> bool {
-        !matches!(self.status, ServiceStatus::Critical)
+        !matches!(self.get_status(), ServiceStatus::Critical)
     }
 
-    pub fn increment_failure_counts(&mut self) -> i64 {
-        self.failure_counts += 1;
-        self.failure_counts
+    pub fn increment_failure_counts(&self) -> i64 {
+        self.inner
+            .consecutive_failures
+            .fetch_add(1, Ordering::Relaxed);
+        self.inner.failure_counts.fetch_add(1, Ordering::Relaxed) + 1
     }
 
-    pub fn increment_request_counts(&mut self) -> i64 {
-        self.total_requests += 1;
-        self.total_requests
+    pub fn increment_request_counts(&self) -> i64 {
+        self.inner.total_requests.fetch_add(1, Ordering::Relaxed) + 1
     }
 
-    pub fn increment_active_requests(&mut self) -> i64 {
-        self.last_active_requests_count_change_timestamp = Local::now().timestamp_millis();
-        self.active_requests_count += 1;
-        self.active_requests_count
+    pub fn increment_active_requests(&self) -> i64 {
+        self.inner
+            .last_active_requests_count_change_timestamp
+            .store(Local::now().timestamp_millis(), Ordering::Release);
+        self.inner
+            .active_requests_count
+            .fetch_add(1, Ordering::AcqRel)
+            + 1
     }
 
-    pub fn decrement_active_requests(&mut self) -> i64 {
-        if self.active_requests_count < 1 {
-            return 0;
+    pub fn decrement_active_requests(&self) -> i64 {
+        let decremented = self.inner.active_requests_count.fetch_update(
+            Ordering::AcqRel,
+            Ordering::Acquire,
+            |count| (count >= 1).then(|| count - 1),
+        );
+        match decremented {
+            Ok(previous) => {
+                self.inner
+                    .last_active_requests_count_change_timestamp
+                    .store(Local::now().timestamp_millis(), Ordering::Release);
+                previous - 1
+            }
+            Err(_) => 0,
         }
+    }
 
-        self.last_active_requests_count_change_timestamp = Local::now().timestamp_millis();
-        self.active_requests_count -= 1;
-        self.active_requests_count
+    pub fn set_connection_failed_time(&self, message: Option<String>) {
+        self.inner
+            .last_connection_failed_timestamp
+            .store(Local::now().timestamp_millis(), Ordering::Relaxed);
+        *self.inner.last_connection_failed_message.lock().unwrap() = message.unwrap_or_default();
+        self.set_status(ServiceStatus::Critical);
     }
 
-    pub fn set_connection_failed_time(&mut self, message: Option<String>) {
-        self.last_connection_failed_timestamp = Some(Local::now().timestamp_millis());
-        self.last_connection_failed_message = message.unwrap_or_default();
-        self.status = ServiceStatus::Critical;
+    /// Timestamp in milliseconds and message of the last connection failure
+    pub fn get_last_connection_failure(&self) -> Option<(i64, String)> {
+        match self
+            .inner
+            .last_connection_failed_timestamp
+            .load(Ordering::Relaxed)
+        {
+            0 => None,
+            timestamp => Some((
+                timestamp,
+                self.inner
+                    .last_connection_failed_message
+                    .lock()
+                    .unwrap()
+                    .clone(),
+            )),
+        }
     }
 
-    pub fn set_first_connection_time(&mut self) {
-        self.first_connection_timestamp = Local::now().timestamp_millis();
+    pub fn set_first_connection_time(&self) {
+        self.inner
+            .first_connection_timestamp
+            .store(Local::now().timestamp_millis(), Ordering::Relaxed);
     }
 
-    pub fn set_response_time(&mut self, time: i64) {
-        if !self.fixed_weight {
-            self.weight = time - self.response_time_avg;
+    /// Records the response time of a request in milliseconds
+    pub fn set_response_time(&self, time: i64) {
+        if !self.inner.fixed_weight.load(Ordering::Relaxed) {
+            self.inner
+                .weight
+                .store(time - self.get_response_time_avg(), Ordering::Relaxed);
         }
-        self.response_time_avg =
-            (self.response_time_avg * (self.total_requests - 1) + time) / self.total_requests;
-        self.response_time_max = std::cmp::max(self.response_time_max, time);
+        self.inner
+            .response_time_total
+            .fetch_add(time, Ordering::AcqRel);
+        self.inner
+            .response_time_samples
+            .fetch_add(1, Ordering::AcqRel);
+        self.inner
+            .response_time_max
+            .fetch_max(time, Ordering::Relaxed);
+    }
+
+    /// Records a request for as long as the returned guard lives: active
+    /// requests are incremented now, and decremented along with recording
+    /// the response time and failure when it drops
+    pub fn acquire(&self) -> RequestGuard {
+        if self.increment_request_counts() == 1 {
+            self.set_first_connection_time();
+        }
+        self.increment_active_requests();
+        RequestGuard {
+            state: self.clone(),
+            started: Instant::now(),
+            failed: false,
+        }
+    }
+}
+
+/// #### RequestGuard
+/// A request in flight to an instance, see [ServiceInstanceState::acquire]
+#[must_use = "the request is recorded as finished when the guard drops"]
+pub struct RequestGuard {
+    state: ServiceInstanceState,
+    started: Instant,
+    failed: bool,
+}
+
+impl RequestGuard {
+    /// Records the request as failed when the guard drops
+    pub fn fail(&mut self) {
+        self.failed = true;
+    }
+
+    pub fn state(&self) -> &ServiceInstanceState {
+        &self.state
+    }
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        self.state.decrement_active_requests();
+        self.state
+            .set_response_time(self.started.elapsed().as_millis() as i64);
+        match self.failed {
+            true => {
+                self.state.increment_failure_counts();
+            }
+            false => self.state.reset_consecutive_failures(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_records_response_times_without_requests() {
+        let state = ServiceInstanceState::new(None);
+        state.set_response_time(30);
+        state.set_response_time(10);
+
+        assert_eq!(state.get_response_time_avg(), 20);
+        assert_eq!(state.get_response_time_max(), 30);
+    }
+
+    #[test]
+    fn it_records_requests_when_guards_drop() {
+        let state = ServiceInstanceState::new(None);
+        let first = state.acquire();
+        let mut second = state.clone().acquire();
+        assert_eq!(state.get_active_requests_count(None), 2);
+
+        second.fail();
+        drop(second);
+        assert_eq!(state.get_consecutive_failures(), 1);
+        drop(first);
+
+        assert_eq!(state.get_active_requests_count(None), 0);
+        assert_eq!(state.get_total_requests(), 2);
+        assert_eq!(state.get_failure_counts(), 1);
+        assert_eq!(state.get_consecutive_failures(), 0);
+    }
+
+    #[test]
+    fn it_shares_counters_between_threads() {
+        let state = ServiceInstanceState::new(None);
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let state = state.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        let _guard = state.acquire();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        assert_eq!(state.get_total_requests(), 800);
+        assert_eq!(state.get_active_requests_count(None), 0);
+    }
+
+    #[test]
+    fn it_resets_stale_active_requests() {
+        let state = ServiceInstanceState::new(None);
+        state.set_active_requests_count_timeout(1);
+        let _guard = state.acquire();
+
+        let later = Local::now().timestamp_millis() + 2000;
+        assert_eq!(state.get_active_requests_count(None), 1);
+        assert_eq!(state.get_active_requests_count(Some(later)), 0);
     }
 }
@@ -1,4 +1,6 @@
-
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
 
 pub trait NorthStateDataClone {
     fn clone_box(&self) -> Box<dyn NorthStateData>;
@@ -20,3 +22,69 @@ impl Clone for Box<dyn NorthStateData> {
 }
 
 pub trait NorthStateData: NorthStateDataClone + Send + Sync {}
+
+/// ## NorthStateMap
+/// Type map holding the state data registered on a service. Unlike the boxed
+/// state list, values can be looked up by their concrete type.
+#[derive(Clone, Default)]
+pub struct NorthStateMap {
+    entries: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl NorthStateMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a state value, replacing any previous value of the same type
+    pub fn insert<T: NorthStateData + 'static>(&mut self, data: T) {
+        self.entries.insert(TypeId::of::<T>(), Arc::new(data));
+    }
+
+    /// Returns the state value of type `T`
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.entries
+            .get(&TypeId::of::<T>())
+            .and_then(|data| data.downcast_ref::<T>())
+    }
+
+    /// Returns a shared pointer to the state value of type `T`
+    pub fn get_arc<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.entries
+            .get(&TypeId::of::<T>())
+            .and_then(|data| data.clone().downcast::<T>().ok())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::{NorthStateData, NorthStateMap};
+
+    #[derive(Clone)]
+    struct TestData {
+        pub title: String,
+    }
+
+    impl NorthStateData for TestData {}
+
+    #[test]
+    fn it_can_get_state_by_type() {
+        let mut state = NorthStateMap::new();
+        state.insert(TestData {
+            title: "north".to_string(),
+        });
+
+        assert_eq!(state.len(), 1);
+        assert_eq!(state.get::<TestData>().unwrap().title, "north");
+        assert_eq!(state.get_arc::<TestData>().unwrap().title, "north");
+        assert!(state.get::<String>().is_none());
+    }
+}
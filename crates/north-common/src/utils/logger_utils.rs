@@ -1,11 +1,151 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
 use yansi::Paint;
 
 use log::{Level, LevelFilter, Metadata, Record};
 use log4rs::append::console::ConsoleAppender;
-use log4rs::append::file::FileAppender;
+use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
+use log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger;
+use log4rs::append::rolling_file::policy::compound::trigger::time::{
+    TimeTrigger, TimeTriggerConfig, TimeTriggerInterval,
+};
+use log4rs::append::rolling_file::policy::compound::trigger::Trigger;
+use log4rs::append::rolling_file::policy::compound::CompoundPolicy;
+use log4rs::append::rolling_file::RollingFileAppender;
+use log4rs::append::Append;
 use log4rs::config::{Appender, Logger as LoggerRs, Root};
+use log4rs::encode::json::JsonEncoder;
 use log4rs::encode::pattern::PatternEncoder;
-use log4rs::Config;
+use log4rs::encode::Encode;
+use log4rs::{Config, Handle};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+
+/// Name used for the root logger in [log_levels] and [set_log_level]
+pub const ROOT_LOGGER: &str = "root";
+
+/// Target of access log records, written to the `requests` file when one is
+/// configured
+pub const REQUESTS_LOGGER: &str = "north::requests";
+
+/// Environment variable holding an env-filter style directive list, e.g
+/// `info,hyper=warn,north::web=debug`. It overrides [LoggerOptions::filter]
+pub const LOG_FILTER_ENV: &str = "RUST_LOG";
+
+const DEFAULT_PATTERN: &str = "[North] {h({d(%Y-%m-%d %H:%M:%S %z)})} {l} [{t}] - {m}{n}";
+
+/// Output format of a log appender
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Console,
+
+    /// One JSON object per line
+    Json,
+}
+
+/// When a log file is rolled over
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    /// Once the file grows past the given size in bytes
+    Size(u64),
+    Hourly,
+    Daily,
+}
+
+/// ## LogFileOptions
+/// A log file with optional rotation
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogFileOptions {
+    pub path: PathBuf,
+
+    /// @defaults to Console
+    #[serde(default)]
+    pub format: LogFormat,
+
+    /// Rotation of the file. `None` appends to the same file forever
+    ///
+    /// @defaults to [None]
+    #[serde(default)]
+    pub rotation: Option<LogRotation>,
+
+    /// Number of rolled files kept next to the active one
+    ///
+    /// @defaults to 7
+    #[serde(default = "default_retention")]
+    pub retention: u32,
+}
+
+fn default_retention() -> u32 {
+    7
+}
+
+impl LogFileOptions {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        LogFileOptions {
+            path: path.into(),
+            format: LogFormat::default(),
+            rotation: None,
+            retention: default_retention(),
+        }
+    }
+}
+
+/// ## LoggerOptions
+/// Logging configuration used by [init_logger_with]. It can be deserialized
+/// from the service configuration.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggerOptions {
+    /// Format of the console output
+    ///
+    /// @defaults to Console
+    pub format: LogFormat,
+
+    /// Env-filter style directives: a default level followed by per-module
+    /// levels, e.g `info,hyper=warn,north::web=debug`
+    ///
+    /// @defaults to debug,hyper=warn,reqwest=warn,arangors_lite::response=info
+    pub filter: String,
+
+    /// File receiving every log record besides the console
+    ///
+    /// @defaults to [None]
+    pub file: Option<LogFileOptions>,
+
+    /// File receiving access log records only. Without it access logs go to
+    /// the console
+    ///
+    /// @defaults to [None]
+    pub requests: Option<LogFileOptions>,
+}
+
+impl Default for LoggerOptions {
+    fn default() -> Self {
+        LoggerOptions {
+            format: LogFormat::default(),
+            filter: "debug,hyper=warn,reqwest=warn,arangors_lite::response=info".to_string(),
+            file: None,
+            requests: None,
+        }
+    }
+}
+
+struct LoggerState {
+    handle: Handle,
+    options: LoggerOptions,
+    levels: BTreeMap<String, LevelFilter>,
+}
+
+static LOGGER_STATE: OnceLock<Mutex<LoggerState>> = OnceLock::new();
 
 pub fn print_format(name: &str, value: &str) {
     println!(
@@ -31,27 +171,226 @@ impl log::Log for Logger {
     fn flush(&self) {}
 }
 
-pub fn init_logger() {
+/// Parses env-filter style directives into levels per logger. A directive
+/// without a module sets the [ROOT_LOGGER] level
+pub fn parse_filter(filter: &str) -> Result<BTreeMap<String, LevelFilter>, Error> {
+    let mut levels = BTreeMap::new();
+    for directive in filter.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+        let (name, level) = match directive.split_once('=') {
+            Some((name, level)) => (name.trim(), level.trim()),
+            None => (ROOT_LOGGER, directive),
+        };
+        let level = LevelFilter::from_str(level)
+            .map_err(|_| Error::LoggerError(format!("unknown log level in {directive}")))?;
+        levels.insert(name.to_string(), level);
+    }
+    Ok(levels)
+}
+
+fn encoder(format: LogFormat) -> Box<dyn Encode> {
+    match format {
+        LogFormat::Console => Box::new(PatternEncoder::new(DEFAULT_PATTERN)),
+        LogFormat::Json => Box::new(JsonEncoder::new()),
+    }
+}
+
+fn file_appender(file: &LogFileOptions) -> Result<Box<dyn Append>, Error> {
+    let logger_error = |e: std::io::Error| {
+        Error::LoggerError(format!("cannot open log file {}: {e}", file.path.display()))
+    };
+    if let Some(parent) = file.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(logger_error)?;
+    }
+
+    let trigger: Box<dyn Trigger> = match file.rotation {
+        None => Box::new(SizeTrigger::new(u64::MAX)),
+        Some(LogRotation::Size(limit)) => Box::new(SizeTrigger::new(limit)),
+        Some(LogRotation::Hourly) => Box::new(time_trigger(TimeTriggerInterval::Hour(1))),
+        Some(LogRotation::Daily) => Box::new(time_trigger(TimeTriggerInterval::Day(1))),
+    };
+    let pattern = format!("{}.{{}}", file.path.display());
+    let roller = FixedWindowRoller::builder()
+        .build(&pattern, file.retention.max(1))
+        .map_err(|e| Error::LoggerError(e.to_string()))?;
+
+    let appender = RollingFileAppender::builder()
+        .encoder(encoder(file.format))
+        .build(
+            &file.path,
+            Box::new(CompoundPolicy::new(trigger, Box::new(roller))),
+        )
+        .map_err(logger_error)?;
+    Ok(Box::new(appender))
+}
+
+fn time_trigger(interval: TimeTriggerInterval) -> TimeTrigger {
+    TimeTrigger::new(TimeTriggerConfig {
+        interval,
+        modulate: true,
+        max_random_delay: 0,
+    })
+}
+
+fn build_config(
+    options: &LoggerOptions,
+    levels: &BTreeMap<String, LevelFilter>,
+) -> Result<Config, Error> {
     let stdout = ConsoleAppender::builder()
-        .encoder(Box::new(PatternEncoder::new(
-            "[North] {h({d(%Y-%m-%d %H:%M:%S %z)})} {l} [{t}] - {m}{n}",
-        )))
+        .encoder(encoder(options.format))
         .build();
 
-    let requests = FileAppender::builder()
-        .encoder(Box::new(PatternEncoder::new(
-            "[North] {h({d(%Y-%m-%d %H:%M:%S %z)})} {l} [{t}] - {m}{n}",
-        )))
-        .build("log/requests.log")
-        .unwrap();
-
-    let config = Config::builder()
-        .appender(Appender::builder().build("stdout", Box::new(stdout)))
-        .appender(Appender::builder().build("requests", Box::new(requests)))
-        .logger(LoggerRs::builder().build("arangors_lite::response", LevelFilter::Info))
-        .logger(LoggerRs::builder().build("hyper", LevelFilter::Warn))
-        .logger(LoggerRs::builder().build("reqwest", LevelFilter::Warn))
-        .build(Root::builder().appender("stdout").build(LevelFilter::Debug))
-        .unwrap();
-    if log4rs::init_config(config).is_ok() {};
+    let mut config =
+        Config::builder().appender(Appender::builder().build("stdout", Box::new(stdout)));
+    let mut root = Root::builder().appender("stdout");
+
+    if let Some(file) = options.file.as_ref() {
+        config = config.appender(Appender::builder().build("file", file_appender(file)?));
+        root = root.appender("file");
+    }
+    if let Some(requests) = options.requests.as_ref() {
+        config = config
+            .appender(Appender::builder().build("requests", file_appender(requests)?))
+            .logger(
+                LoggerRs::builder()
+                    .appender("requests")
+                    .additive(false)
+                    .build(REQUESTS_LOGGER, LevelFilter::Info),
+            );
+    }
+
+    for (name, level) in levels.iter().filter(|(name, _)| *name != ROOT_LOGGER) {
+        if name == REQUESTS_LOGGER && options.requests.is_some() {
+            continue;
+        }
+        config = config.logger(LoggerRs::builder().build(name, *level));
+    }
+
+    let root_level = levels
+        .get(ROOT_LOGGER)
+        .copied()
+        .unwrap_or(LevelFilter::Info);
+    config
+        .build(root.build(root_level))
+        .map_err(|e| Error::LoggerError(e.to_string()))
+}
+
+/// Levels of the `RUST_LOG` directives, or of the options filter when it is
+/// unset. Invalid directives are reported on stderr and ignored rather than
+/// leaving the process without a logger.
+fn resolve_levels(
+    env_filter: Option<String>,
+    options: &LoggerOptions,
+) -> Result<BTreeMap<String, LevelFilter>, Error> {
+    if let Some(filter) = env_filter {
+        match parse_filter(&filter) {
+            Ok(levels) => return Ok(levels),
+            Err(e) => eprintln!("[North] ignoring {LOG_FILTER_ENV}: {e}"),
+        }
+    }
+    parse_filter(&options.filter)
+}
+
+/// Initializes logging with the default options. The `RUST_LOG` directives
+/// are applied when set. Errors are reported on stderr instead of panicking.
+pub fn init_logger() {
+    if LOGGER_STATE.get().is_some() {
+        return;
+    }
+    if let Err(e) = init_logger_with(LoggerOptions::default()) {
+        eprintln!("[North] failed to initialize logging: {e}");
+    }
+}
+
+/// Initializes logging with the given options. `tracing` events are bridged
+/// into the same appenders, so no tracing subscriber is needed.
+pub fn init_logger_with(options: LoggerOptions) -> Result<(), Error> {
+    let levels = resolve_levels(env::var(LOG_FILTER_ENV).ok(), &options)?;
+    let config = build_config(&options, &levels)?;
+
+    if let Some(state) = LOGGER_STATE.get() {
+        let mut state = state.lock().unwrap();
+        state.handle.set_config(config);
+        state.options = options;
+        state.levels = levels;
+        return Ok(());
+    }
+
+    let handle = log4rs::init_config(config).map_err(|e| Error::LoggerError(e.to_string()))?;
+    let _ = LOGGER_STATE.set(Mutex::new(LoggerState {
+        handle,
+        options,
+        levels,
+    }));
+    Ok(())
+}
+
+/// Returns the configured level of every logger, including [ROOT_LOGGER]
+pub fn log_levels() -> BTreeMap<String, LevelFilter> {
+    match LOGGER_STATE.get() {
+        Some(state) => state.lock().unwrap().levels.clone(),
+        None => BTreeMap::new(),
+    }
+}
+
+/// Changes the level of a logger at runtime. `level` is one of `off`,
+/// `error`, `warn`, `info`, `debug` or `trace`
+pub fn set_log_level(name: &str, level: &str) -> Result<(), String> {
+    let level = LevelFilter::from_str(level).map_err(|_| format!("unknown log level {level}"))?;
+    let state = LOGGER_STATE
+        .get()
+        .ok_or_else(|| "the logger is not initialized".to_string())?;
+
+    let mut state = state.lock().unwrap();
+    let mut levels = state.levels.clone();
+    levels.insert(name.to_string(), level);
+    let config = build_config(&state.options, &levels).map_err(|e| e.to_string())?;
+    state.handle.set_config(config);
+    state.levels = levels;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_env_filter_directives() {
+        let levels = parse_filter("info, hyper=warn,north::web=debug").unwrap();
+
+        assert_eq!(levels.get(ROOT_LOGGER), Some(&LevelFilter::Info));
+        assert_eq!(levels.get("hyper"), Some(&LevelFilter::Warn));
+        assert_eq!(levels.get("north::web"), Some(&LevelFilter::Debug));
+        assert!(parse_filter("hyper=loud").is_err());
+    }
+
+    #[test]
+    fn it_falls_back_to_the_options_filter_on_invalid_env_directives() {
+        let options = LoggerOptions {
+            filter: "warn".to_string(),
+            ..LoggerOptions::default()
+        };
+
+        let levels = resolve_levels(Some("hyper=loud".to_string()), &options).unwrap();
+        assert_eq!(levels.get(ROOT_LOGGER), Some(&LevelFilter::Warn));
+
+        let levels = resolve_levels(Some("debug".to_string()), &options).unwrap();
+        assert_eq!(levels.get(ROOT_LOGGER), Some(&LevelFilter::Debug));
+    }
+
+    #[test]
+    fn it_builds_a_config_without_files() {
+        let options = LoggerOptions::default();
+        let levels = parse_filter(&options.filter).unwrap();
+        assert!(build_config(&options, &levels).is_ok());
+    }
+
+    #[test]
+    fn it_reports_unwritable_log_files() {
+        let options = LoggerOptions {
+            file: Some(LogFileOptions::new("/proc/north/service.log")),
+            ..LoggerOptions::default()
+        };
+        let levels = parse_filter(&options.filter).unwrap();
+        assert!(build_config(&options, &levels).is_err());
+    }
 }
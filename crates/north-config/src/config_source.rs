@@ -4,16 +4,22 @@ use json_dotpath::DotPaths;
 use serde::de;
 use serde_json::Value;
 use std::fmt::{Debug, Formatter};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::sync::{Arc, Mutex, RwLock};
 
 #[cfg(any(feature = "tokio", feature = "async-std"))]
 use async_trait::async_trait;
-#[cfg(not(any(feature = "tokio", feature = "async-std")))]
-use std::io::Read;
 
-use crate::error::Error;
-use crate::utils::{import_env_vars, preamble};
+use crate::coerce::Coerce;
+#[cfg(feature = "consul")]
+use crate::consul_source::ConsulSourceOptions;
+use crate::error::{Error, SourceName};
+use crate::interpolate::{escape, escape_value, interpolate};
+use crate::utils::{env_file_path, import_env_vars, preamble};
+use crate::watcher::watch_file_path;
 pub use convert_case::Case;
+use notify::RecommendedWatcher;
 
 /// Trait for cloning a boxed `CustomConfigSource` object.
 /// This is used in cases where we need to clone a trait object without knowing its concrete type.
@@ -50,9 +56,16 @@ impl Debug for Box<dyn CustomConfigSource> {
 /// (`Sync`).
 #[cfg(not(any(feature = "tokio", feature = "async-std")))]
 pub trait CustomConfigSource: CustomConfigSourceClone + Send + Sync {
-    /// This is the only implementable member.
+    /// This is the only required member.
     /// Here you can return a serde value
     fn get_config_value(&self) -> Result<Value, Error>;
+
+    /// Name of the source in errors
+    ///
+    /// @defaults to the type name
+    fn name(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
 }
 
 /// Allows you to provide a custom config source that can be accessed asynchronously.
@@ -61,17 +74,25 @@ pub trait CustomConfigSource: CustomConfigSourceClone + Send + Sync {
 #[cfg(any(feature = "tokio", feature = "async-std"))]
 #[async_trait]
 pub trait CustomConfigSource: CustomConfigSourceClone + Send + Sync {
-    /// This is the only implementable member.
+    /// This is the only required member.
     /// Here you can return a serde value
     async fn get_config_value(&self) -> Result<Value, Error>;
+
+    /// Name of the source in errors
+    ///
+    /// @defaults to the type name
+    fn name(&self) -> String {
+        std::any::type_name::<Self>().to_string()
+    }
 }
 
 /// Represents the source of configuration values.
 ///
-/// This enum has three variants:
+/// This enum has four variants:
 /// - `Env`: Represents loading configuration values from OS environment variables.
 /// - `File`: Represents loading configuration values from a JSON, YAML, or TOML file.
 /// - `Custom`: Represents loading configuration values using a custom implementation of `CustomConfigSource`.
+/// - `Consul`: Represents loading configuration values from Consul KV, with the `consul` feature.
 ///
 /// # Examples
 ///
@@ -143,6 +164,24 @@ pub enum ConfigSource {
 
     /// loads a json, YAML, OR TOML file
     Custom(Box<dyn CustomConfigSource>),
+
+    /// # Consul
+    /// loads from Consul KV, either nested keys under a prefix or a single
+    /// JSON or YAML document, and reloads them on change when watched
+    ///
+    /// # Example
+    ///
+    /// ```rust,ignore
+    /// use north_config::{ConfigSource, ConsulFormat, ConsulSourceOptions};
+    /// ConfigSource::Consul(ConsulSourceOptions {
+    ///     prefix: "config/billing".to_string(),
+    ///     format: ConsulFormat::Keys,
+    ///     watch: true,
+    ///     ..Default::default()
+    /// });
+    /// ```
+    #[cfg(feature = "consul")]
+    Consul(ConsulSourceOptions),
 }
 
 impl Default for ConfigSource {
@@ -277,19 +316,235 @@ impl Default for FileSourceOptions {
 ///
 /// ## Fields
 /// - `value`: The actual configuration value of type `T`. It can be accessed and modified directly.
+///   It is the value loaded on creation, see [NorthConfig::current_value] for the reloaded one.
 #[derive(Debug, Clone, Default)]
 pub struct NorthConfig<T>
 where
     T: Clone + de::DeserializeOwned,
 {
     pub value: T,
+    state: Arc<ConfigState<T>>,
 }
 
 impl<T: Clone + de::DeserializeOwned> NorthConfig<T> {
+    fn new(value: T, layers: Vec<Value>, validator: Option<Validator>) -> Self {
+        NorthConfig {
+            state: Arc::new(ConfigState {
+                current: RwLock::new(Arc::new(value.clone())),
+                layers: Mutex::new(layers),
+                validator,
+                listeners: Default::default(),
+                watchers: Default::default(),
+            }),
+            value,
+        }
+    }
+
     /// access the configuration
     pub fn get_value(&self) -> &T {
         &self.value
     }
+
+    /// access the latest configuration, which differs from [NorthConfig::get_value]
+    /// once a watched source was reloaded
+    pub fn current_value(&self) -> Arc<T> {
+        self.state.current.read().unwrap().clone()
+    }
+}
+
+impl<T: Clone + de::DeserializeOwned + Send + Sync + 'static> NorthConfig<T> {
+    /// Calls `listener` on every reload of a watched source, from the thread
+    /// or task that watches it
+    pub fn on_change<F>(&self, listener: F)
+    where
+        F: Fn(&ConfigEvent<T>) + Send + Sync + 'static,
+    {
+        self.state
+            .listeners
+            .lock()
+            .unwrap()
+            .push(Box::new(move |event| {
+                listener(event);
+                true
+            }));
+    }
+
+    /// Receives an event on every reload of a watched source, until the
+    /// receiver is dropped
+    pub fn subscribe(&self) -> Receiver<ConfigEvent<T>> {
+        let (sender, receiver) = channel();
+        let sender = Mutex::new(sender);
+        self.state
+            .listeners
+            .lock()
+            .unwrap()
+            .push(Box::new(move |event| {
+                sender.lock().unwrap().send(event.clone()).is_ok()
+            }));
+        receiver
+    }
+}
+
+/// # ConfigEvent
+///
+/// Outcome of a reload, see [NorthConfig::on_change] and [NorthConfig::subscribe]
+#[derive(Debug)]
+pub enum ConfigEvent<T> {
+    /// A watched source changed and the configuration was swapped
+    Changed { old: Arc<T>, new: Arc<T> },
+
+    /// A watched source failed to load or the merged configuration failed to
+    /// deserialize, the previous configuration was kept
+    ReloadFailed(Arc<Error>),
+}
+
+impl<T> Clone for ConfigEvent<T> {
+    fn clone(&self) -> Self {
+        match self {
+            ConfigEvent::Changed { old, new } => ConfigEvent::Changed {
+                old: old.clone(),
+                new: new.clone(),
+            },
+            ConfigEvent::ReloadFailed(error) => ConfigEvent::ReloadFailed(error.clone()),
+        }
+    }
+}
+
+/// Listener of reloads, returning `false` once it should be dropped
+type Listener<T> = Box<dyn Fn(&ConfigEvent<T>) -> bool + Send + Sync>;
+
+/// Old and new configurations of a reload
+type Swap<T> = (Arc<T>, Arc<T>);
+
+/// Validates a merged config before it is deserialized
+pub(crate) type Validator = Box<dyn Fn(&Value) -> Result<(), Error> + Send + Sync>;
+
+/// Latest configuration and the values of the sources it was merged from,
+/// shared by the clones of a [NorthConfig] and its watchers
+#[derive(Default)]
+pub(crate) struct ConfigState<T> {
+    current: RwLock<Arc<T>>,
+
+    /// value of every source in order, null when the source was skipped
+    layers: Mutex<Vec<Value>>,
+
+    /// validates every merged config, on load and on reloads
+    validator: Option<Validator>,
+
+    listeners: Mutex<Vec<Listener<T>>>,
+
+    /// file watchers, stopped along with the config
+    watchers: Mutex<Vec<RecommendedWatcher>>,
+}
+
+impl<T: Debug> Debug for ConfigState<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConfigState")
+            .field("current", &self.current)
+            .field("layers", &self.layers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Clone + de::DeserializeOwned> ConfigState<T> {
+    /// Replaces the value of the source at `index` and swaps the configuration
+    /// for the merged one, returning the old and new configurations. The
+    /// configuration is kept when the merged value doesn't deserialize, and
+    /// `None` returned when the source didn't change
+    fn update_layer(&self, index: usize, value: Value) -> Result<Option<Swap<T>>, Error> {
+        let mut layers = self.layers.lock().unwrap();
+        if layers[index] == value {
+            return Ok(None);
+        }
+
+        let mut updated = layers.clone();
+        updated[index] = value;
+        let merged = Arc::new(load_value::<T>(&updated, self.validator.as_ref())?);
+
+        *layers = updated;
+        let old = std::mem::replace(&mut *self.current.write().unwrap(), merged.clone());
+        Ok(Some((old, merged)))
+    }
+
+    /// Reloads the source at `index` with its new value, notifying listeners
+    /// of the change, or of the error while keeping the previous configuration
+    pub(crate) fn reload_layer(&self, index: usize, value: Result<Value, Error>) {
+        let event = match value.and_then(|value| self.update_layer(index, value)) {
+            Ok(Some((old, new))) => ConfigEvent::Changed { old, new },
+            Ok(None) => return,
+            Err(error) => {
+                log::error!(
+                    "Config was not reloaded, keeping the previous value: {}",
+                    error
+                );
+                ConfigEvent::ReloadFailed(Arc::new(error))
+            }
+        };
+
+        self.listeners
+            .lock()
+            .unwrap()
+            .retain(|listener| listener(&event));
+    }
+
+    fn keep_watcher(&self, watcher: RecommendedWatcher) {
+        self.watchers.lock().unwrap().push(watcher);
+    }
+}
+
+/// Merges the value of every source, last one overriding the others
+fn merge_layers(layers: &[Value]) -> Value {
+    let mut current_value = Value::default();
+    for layer in layers.iter().filter(|layer| !layer.is_null()) {
+        current_value.merge(layer.clone());
+    }
+    current_value
+}
+
+/// Merges the value of every source then resolves the placeholders of the
+/// merged config, so they can reference keys of any source. Runs here rather
+/// than in `resolve_source`, as reloads replace a single layer and merge the
+/// layers again without going through it
+fn resolve_layers(layers: &[Value]) -> Result<Value, Error> {
+    interpolate(merge_layers(layers))
+}
+
+/// Merges the layers into the configuration, validating it first when the
+/// config has a validator
+fn load_value<T: de::DeserializeOwned>(
+    layers: &[Value],
+    validator: Option<&Validator>,
+) -> Result<T, Error> {
+    let value = resolve_layers(layers)?;
+    if let Some(validate) = validator {
+        validate(&value)?;
+    }
+    deserialize_value::<T>(value)
+}
+
+/// Deserializes the merged config, pointing at the key that failed
+fn deserialize_value<T: de::DeserializeOwned>(value: Value) -> Result<T, Error> {
+    serde_path_to_error::deserialize::<_, T>(Coerce(value)).map_err(|error| {
+        let path = error.path().to_string();
+        let message = error.into_inner().to_string();
+
+        // serde reports mismatches as "invalid type: <actual>, expected <expected>"
+        let mismatch = message
+            .strip_prefix("invalid type: ")
+            .or_else(|| message.strip_prefix("invalid value: "))
+            .and_then(|mismatch| mismatch.split_once(", expected "));
+        let (actual, expected) = match mismatch {
+            Some((actual, expected)) => (Some(actual.to_string()), Some(expected.to_string())),
+            None => (None, None),
+        };
+
+        Error::DeserializeError {
+            path,
+            expected,
+            actual,
+            message,
+        }
+    })
 }
 
 /// # new_config
@@ -313,14 +568,82 @@ impl<T: Clone + de::DeserializeOwned> NorthConfig<T> {
 ///  let config = north_config::new_config::<DemoConfig>(config_options).await;
 ///  let config_val = config.get_value();
 /// ```
+///
+/// # Panics
+///
+/// This function panics with the error of [try_new_config] when the config fails to load.
 #[cfg(any(feature = "tokio", feature = "async-std"))]
-pub async fn new_config<T: Clone + de::DeserializeOwned>(
+pub async fn new_config<T: Clone + de::DeserializeOwned + Send + Sync + 'static>(
     option: NorthConfigOptions,
 ) -> NorthConfig<T> {
-    preamble();
+    try_new_config(option)
+        .await
+        .unwrap_or_else(|error| panic!("{}", error))
+}
 
-    let value = resolve_source::<T>(option).await;
-    NorthConfig { value }
+/// # try_new_config
+///
+/// creates a new instance of North Config like [new_config], returning an
+/// error naming the failing source, or the key that failed to deserialize,
+/// instead of panicking
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+pub async fn try_new_config<T: Clone + de::DeserializeOwned + Send + Sync + 'static>(
+    option: NorthConfigOptions,
+) -> Result<NorthConfig<T>, Error> {
+    load_config(option, None).await
+}
+
+/// # new_validated_config
+///
+/// creates a new instance of North Config like [new_config], validating the
+/// merged config against the JSON Schema of `T` before deserializing it, on
+/// load and on reloads
+///
+/// # Panics
+///
+/// This function panics with the error of [try_new_validated_config] when the config fails to load.
+#[cfg(all(feature = "schema", any(feature = "tokio", feature = "async-std")))]
+pub async fn new_validated_config<T>(
+    option: NorthConfigOptions,
+    schema_options: crate::SchemaOptions,
+) -> NorthConfig<T>
+where
+    T: Clone + de::DeserializeOwned + schemars::JsonSchema + Send + Sync + 'static,
+{
+    try_new_validated_config(option, schema_options)
+        .await
+        .unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// # try_new_validated_config
+///
+/// creates a new instance of North Config like [new_validated_config],
+/// returning an [Error::ValidationError] listing every violation of the
+/// schema instead of panicking
+#[cfg(all(feature = "schema", any(feature = "tokio", feature = "async-std")))]
+pub async fn try_new_validated_config<T>(
+    option: NorthConfigOptions,
+    schema_options: crate::SchemaOptions,
+) -> Result<NorthConfig<T>, Error>
+where
+    T: Clone + de::DeserializeOwned + schemars::JsonSchema + Send + Sync + 'static,
+{
+    let validator = crate::schema::schema_validator::<T>(&schema_options)?;
+    load_config(option, Some(validator)).await
+}
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+async fn load_config<T: Clone + de::DeserializeOwned + Send + Sync + 'static>(
+    option: NorthConfigOptions,
+    validator: Option<Validator>,
+) -> Result<NorthConfig<T>, Error> {
+    preamble()?;
+
+    let layers = resolve_source(option.clone()).await?;
+    let value = load_value::<T>(&layers, validator.as_ref())?;
+    let config = NorthConfig::new(value, layers, validator);
+    watch_sources(&config, option.sources);
+    Ok(config)
 }
 
 /// Creates a new `NorthConfig` with the specified options.
@@ -357,13 +680,86 @@ pub async fn new_config<T: Clone + de::DeserializeOwned>(
 ///
 /// # Panics
 ///
-/// This function may panic if the source for deserialization is missing or if deserialization fails.
+/// This function panics with the error of [try_new_config] when the config fails to load.
+#[cfg(not(any(feature = "tokio", feature = "async-std")))]
+pub fn new_config<T: Clone + de::DeserializeOwned + Send + Sync + 'static>(
+    option: NorthConfigOptions,
+) -> NorthConfig<T> {
+    try_new_config(option).unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// Creates a new `NorthConfig` like [new_config], returning an error naming
+/// the failing source, or the key that failed to deserialize, instead of
+/// panicking.
+///
+/// # Example
+///
+/// ```rust
+/// use north_config::{ConfigSource, Error, NorthConfigOptions, SourceName};
+///
+/// #[derive(Debug, serde::Deserialize, Clone)]
+/// struct MyConfig {
+///    pub host: String
+/// }
+///
+/// let option = NorthConfigOptions::new(vec![ConfigSource::File("missing.json".to_string(), None)]);
+/// match north_config::try_new_config::<MyConfig>(option) {
+///     Err(Error::SourceError { source: SourceName::File(path), .. }) => assert!(path.ends_with("missing.json")),
+///     _ => unreachable!(),
+/// }
+/// ```
 #[cfg(not(any(feature = "tokio", feature = "async-std")))]
-pub fn new_config<T: Clone + de::DeserializeOwned>(option: NorthConfigOptions) -> NorthConfig<T> {
-    preamble();
+pub fn try_new_config<T: Clone + de::DeserializeOwned + Send + Sync + 'static>(
+    option: NorthConfigOptions,
+) -> Result<NorthConfig<T>, Error> {
+    load_config(option, None)
+}
 
-    let value = resolve_source::<T>(option);
-    NorthConfig { value }
+/// Creates a new `NorthConfig` like [new_config], validating the merged
+/// config against the JSON Schema of `T` before deserializing it, on load and
+/// on reloads.
+///
+/// # Panics
+///
+/// This function panics with the error of [try_new_validated_config] when the config fails to load.
+#[cfg(all(feature = "schema", not(any(feature = "tokio", feature = "async-std"))))]
+pub fn new_validated_config<T>(
+    option: NorthConfigOptions,
+    schema_options: crate::SchemaOptions,
+) -> NorthConfig<T>
+where
+    T: Clone + de::DeserializeOwned + schemars::JsonSchema + Send + Sync + 'static,
+{
+    try_new_validated_config(option, schema_options).unwrap_or_else(|error| panic!("{}", error))
+}
+
+/// Creates a new `NorthConfig` like [new_validated_config], returning an
+/// [Error::ValidationError] listing every violation of the schema instead of
+/// panicking.
+#[cfg(all(feature = "schema", not(any(feature = "tokio", feature = "async-std"))))]
+pub fn try_new_validated_config<T>(
+    option: NorthConfigOptions,
+    schema_options: crate::SchemaOptions,
+) -> Result<NorthConfig<T>, Error>
+where
+    T: Clone + de::DeserializeOwned + schemars::JsonSchema + Send + Sync + 'static,
+{
+    let validator = crate::schema::schema_validator::<T>(&schema_options)?;
+    load_config(option, Some(validator))
+}
+
+#[cfg(not(any(feature = "tokio", feature = "async-std")))]
+fn load_config<T: Clone + de::DeserializeOwned + Send + Sync + 'static>(
+    option: NorthConfigOptions,
+    validator: Option<Validator>,
+) -> Result<NorthConfig<T>, Error> {
+    preamble()?;
+
+    let layers = resolve_source(option.clone())?;
+    let value = load_value::<T>(&layers, validator.as_ref())?;
+    let config = NorthConfig::new(value, layers, validator);
+    watch_sources(&config, option.sources);
+    Ok(config)
 }
 
 /// Asynchronously resolves the configuration source for the given options.
@@ -372,19 +768,13 @@ pub fn new_config<T: Clone + de::DeserializeOwned>(option: NorthConfigOptions) -
 ///
 /// * `option` - The NorthConfigOptions containing the configuration sources.
 ///
-/// # Constraints
-///
-/// The type `T` must implement Clone and serde's DeserializeOwned trait.
-///
 /// # Returns
 ///
-/// The deserialized configuration value of type `T`.
+/// The value of every source in order, null when the source was skipped, or
+/// the error of the first source that failed.
 #[cfg(any(feature = "tokio", feature = "async-std"))]
-async fn resolve_source<T>(option: NorthConfigOptions) -> T
-where
-    T: Clone + de::DeserializeOwned,
-{
-    let mut current_value = Value::default();
+async fn resolve_source(option: NorthConfigOptions) -> Result<Vec<Value>, Error> {
+    let mut layers = Vec::with_capacity(option.sources.len());
     let cargo_path = std::env::var("CARGO_MANIFEST_DIR").unwrap_or("./".to_string());
 
     #[cfg(debug_assertions)]
@@ -394,42 +784,30 @@ where
     let is_release = true;
 
     for s in option.clone().sources {
-        match s {
-            ConfigSource::Env(env_opt) => {
-                let value = resolve_env_source(env_opt);
-                if value.is_some() {
-                    current_value.merge(value.unwrap());
-                }
-            }
+        let value = match s {
+            ConfigSource::Env(env_opt) => resolve_env_source(env_opt),
             ConfigSource::File(original_path, options) => {
-                let value = resolve_file_source(
+                resolve_file_source(
                     cargo_path.clone(),
                     original_path,
                     is_release,
                     options.unwrap_or_default(),
                 )
-                .await;
-                if value.is_some() {
-                    current_value.merge(value.unwrap());
-                }
+                .await
             }
             ConfigSource::Custom(source) => {
                 let rsp = source.get_config_value().await;
-                match rsp {
-                    Ok(value) => {
-                        if value.is_object() {
-                            current_value.merge(value);
-                        }
-                    }
-                    Err(_) => {
-                        println!("Custom config was not loaded")
-                    }
-                }
+                resolve_custom_source(source.name(), rsp)
+            }
+            #[cfg(feature = "consul")]
+            ConfigSource::Consul(consul_opt) => {
+                crate::consul_source::resolve_consul_source(&consul_opt).await
             }
         };
+        layers.push(value?.unwrap_or_default());
     }
 
-    serde_json::from_value::<T>(current_value).unwrap()
+    Ok(layers)
 }
 
 /// Resolves the configuration source based on the given options.
@@ -438,23 +816,13 @@ where
 ///
 /// * `option` - The configuration options.
 ///
-/// # Type Constraints
-///
-/// T must implement the `Clone` and `DeserializeOwned` traits.
-///
 /// # Returns
 ///
-/// The resolved configuration source.
-///
-/// # Panics
-///
-/// This function will panic if the JSON value cannot be deserialized into the specified type.
+/// The value of every source in order, null when the source was skipped, or
+/// the error of the first source that failed.
 #[cfg(not(any(feature = "tokio", feature = "async-std")))]
-fn resolve_source<T>(option: NorthConfigOptions) -> T
-where
-    T: Clone + de::DeserializeOwned,
-{
-    let mut current_value = Value::default();
+fn resolve_source(option: NorthConfigOptions) -> Result<Vec<Value>, Error> {
+    let mut layers = Vec::with_capacity(option.sources.len());
     let cargo_path = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| "./".to_string());
 
     #[cfg(debug_assertions)]
@@ -464,78 +832,86 @@ where
     let is_release = true;
 
     for s in option.sources {
-        match s {
-            ConfigSource::Env(env_opt) => {
-                let value = resolve_env_source(env_opt);
-                if let Some(v) = value {
-                    current_value.merge(v);
-                }
-            }
-            ConfigSource::File(original_path, options) => {
-                let value = resolve_file_source(
-                    cargo_path.clone(),
-                    original_path,
-                    is_release,
-                    options.unwrap_or_default(),
-                );
-                if let Some(v) = value {
-                    current_value.merge(v);
-                }
-            }
+        let value = match s {
+            ConfigSource::Env(env_opt) => resolve_env_source(env_opt),
+            ConfigSource::File(original_path, options) => resolve_file_source(
+                cargo_path.clone(),
+                original_path,
+                is_release,
+                options.unwrap_or_default(),
+            ),
             ConfigSource::Custom(source) => {
                 let rsp = source.get_config_value();
-                match rsp {
-                    Ok(value) => {
-                        if value.is_object() {
-                            current_value.merge(value);
-                        }
-                    }
-                    Err(_) => {
-                        println!("Custom config was not loaded")
-                    }
-                }
+                resolve_custom_source(source.name(), rsp)
             }
         };
+        layers.push(value?.unwrap_or_default());
     }
 
-    serde_json::from_value::<T>(current_value).unwrap()
+    Ok(layers)
 }
 
-/// Resolve env variable source to Serde [Value]
-fn resolve_env_source(env_opt: EnvSourceOptions) -> Option<Value> {
-    let env_options = env_opt.clone();
+/// Keeps the value of a custom source when it is an object, with its
+/// placeholders escaped as it may come from a remote store
+fn resolve_custom_source(name: String, rsp: Result<Value, Error>) -> Result<Option<Value>, Error> {
+    match rsp {
+        Ok(value) => Ok(Some(escape_value(value)).filter(|value| value.is_object())),
+        Err(error) => Err(error.in_source(SourceName::Custom(name), None)),
+    }
+}
 
+/// Resolve env variable source to Serde [Value]
+fn resolve_env_source(env_opt: EnvSourceOptions) -> Result<Option<Value>, Error> {
     // We want to load env if a path is passed
-    if env_opt.env_file_path.is_some() {
-        import_env_vars(env_opt.env_file_path.unwrap().as_str())
+    if let Some(env_file_path) = &env_opt.env_file_path {
+        import_env_vars(env_file_path)?;
     }
 
-    match process_envs(env_options) {
-        Ok(value) => {
-            if !value.is_null() {
-                Some(value)
-            } else {
-                log::error!("Error loading env variables as config");
-                None
-            }
-        }
-        Err(error) => {
-            println!("{:#?}", error);
-            None
+    let prefix = env_prefix(&env_opt);
+    match process_envs(env_opt) {
+        Ok(value) if !value.is_null() => Ok(Some(value)),
+        Ok(_) => {
+            log::error!("Error loading env variables as config");
+            Ok(None)
         }
+        Err(error) => Err(error.in_source(SourceName::Env(prefix), Some("env"))),
     }
 }
 
+fn env_prefix(option: &EnvSourceOptions) -> String {
+    option
+        .prefix
+        .clone()
+        .unwrap_or_else(|| "NORTH_".to_string())
+}
+
+/// Replaces the `{{env}}` placeholder of `original_path` with either "release" or "debug"
+/// depending on the value of `is_release`, and joins it with `cargo_path`
+fn resolve_file_path(
+    cargo_path: &str,
+    original_path: &str,
+    is_release: bool,
+    options: &FileSourceOptions,
+) -> PathBuf {
+    let path = match (options.enabled_environment, is_release) {
+        (true, true) => original_path.replace("{{env}}", "release"),
+        (true, false) => original_path.replace("{{env}}", "debug"),
+        (false, _) => original_path.to_string(),
+    };
+    PathBuf::from(cargo_path).join(path)
+}
+
 /// Resolves the source file specified by `original_path` by replacing the `{{env}}` placeholder
 /// with either "release" or "debug" depending on the value of `is_release`.
 ///
 /// The resolved file path is obtained by joining `original_path` with `cargo_path`.
-/// If the resolved file path does not exist, a panic with an appropriate message is raised.
+/// If the resolved file path does not exist, an [Error::FileNotFound] is returned.
 ///
 /// The resolved file path is then passed to the `read_file_value` function to read the contents of the file asynchronously.
 ///
 /// If the value read from the file is not null, it is returned wrapped in an `Option`.
-/// Otherwise, an error message is logged and `None` is returned.
+/// Otherwise, an error message is logged and `None` is returned. Errors are
+/// skipped the same way with `skip_on_error`.
 ///
 /// # Arguments
 ///
@@ -553,34 +929,15 @@ async fn resolve_file_source(
     original_path: String,
     is_release: bool,
     options: FileSourceOptions,
-) -> Option<Value> {
-    let path = if options.enabled_environment {
-        match is_release {
-            true => original_path.replace("{{env}}", "release"),
-            false => original_path.replace("{{env}}", "debug"),
-        }
-    } else {
-        original_path.clone()
-    };
-
-    let path_buf = PathBuf::from(cargo_path.clone()).join(path.clone());
-    if !path_buf.exists() && !options.skip_on_error {
-        panic!("No file found in path: {}", path.clone());
-    }
-
-    if !path_buf.exists() && options.skip_on_error {
-        return None;
-    }
-
+) -> Result<Option<Value>, Error> {
+    let path_buf = resolve_file_path(&cargo_path, &original_path, is_release, &options);
     let file_path = path_buf.display().to_string();
-    let value = read_file_value(file_path).await;
+    let value = match path_buf.exists() {
+        true => read_file_value(file_path.clone()).await,
+        false => Err(Error::FileNotFound(file_path.clone())),
+    };
 
-    if !value.is_null() {
-        Some(value)
-    } else {
-        log::error!("Error loading config file {original_path}");
-        None
-    }
+    skip_file_errors(file_path, value, &options)
 }
 
 /// Resolves a file source based on the given parameters.
@@ -596,41 +953,47 @@ async fn resolve_file_source(
 /// - `Some(Value)`: The value read from the file source if it exists and is not null.
 /// - `None`: If the file source does not exist or the value read is null.
 ///
-/// # Panics
-///
-/// - If no file is found in the resolved path.
+/// - `Err(Error)`: If no file is found in the resolved path, or it fails to parse,
+///   unless `skip_on_error` is set.
 #[cfg(not(any(feature = "tokio", feature = "async-std")))]
 fn resolve_file_source(
     cargo_path: String,
     original_path: String,
     is_release: bool,
     options: FileSourceOptions,
-) -> Option<Value> {
-    let path = if options.enabled_environment {
-        match is_release {
-            true => original_path.replace("{{env}}", "release"),
-            false => original_path.replace("{{env}}", "debug"),
-        }
-    } else {
-        original_path.clone()
+) -> Result<Option<Value>, Error> {
+    let path_buf = resolve_file_path(&cargo_path, &original_path, is_release, &options);
+    let file_path = path_buf.display().to_string();
+    let value = match path_buf.exists() {
+        true => read_file_value(&path_buf),
+        false => Err(Error::FileNotFound(file_path.clone())),
     };
 
-    let path_buf = PathBuf::from(cargo_path).join(path.clone());
-    if !path_buf.exists() && !options.skip_on_error {
-        panic!("No file found in path: {}", path);
-    }
-    if !path_buf.exists() && options.skip_on_error {
-        return None;
-    }
-
-    let file_path = path_buf.display().to_string();
-    let value = read_file_value(file_path);
+    skip_file_errors(file_path, value, &options)
+}
 
-    if !value.is_null() {
-        Some(value)
-    } else {
-        log::error!("Error loading config file {original_path}");
-        None
+/// Attributes the errors of a file source to it, or skips them with
+/// `skip_on_error`
+fn skip_file_errors(
+    file_path: String,
+    value: Result<Value, Error>,
+    options: &FileSourceOptions,
+) -> Result<Option<Value>, Error> {
+    match value {
+        Ok(value) if !value.is_null() => Ok(Some(value)),
+        Ok(_) => {
+            log::error!("Error loading config file {file_path}");
+            Ok(None)
+        }
+        Err(Error::FileNotFound(_)) if options.skip_on_error => Ok(None),
+        Err(error) if options.skip_on_error => {
+            log::error!("Config file {file_path} was skipped: {error}");
+            Ok(None)
+        }
+        Err(error) => {
+            let format = file_format(&file_path);
+            Err(error.in_source(SourceName::File(file_path), Some(format)))
+        }
     }
 }
 
@@ -646,7 +1009,7 @@ fn resolve_file_source(
 ///
 /// # Example
 ///
-/// ```
+/// ```ignore
 /// use serde_json::Value;
 /// use std::error::Error;
 ///
@@ -686,9 +1049,16 @@ fn process_envs(option: EnvSourceOptions) -> Result<Value, Error> {
         }
 
         let new_key = key.strip_prefix(prefix).expect("env var prefix missing");
-        let dot_key: String = new_key.replace(separator.clone(), ".").clone().to_case(case);
-        let new_value = serde_json::from_str::<serde_json::Value>(value.as_str()).expect("no value");
-        obj.dot_set(dot_key.as_str(), value).unwrap();
+        let dot_key: String = new_key
+            .replace(separator.clone(), ".")
+            .clone()
+            .to_case(case);
+        // env variables are taken literally, their `${` are not placeholders
+        let value = escape(&value);
+        let new_value =
+            serde_json::from_str::<Value>(value.as_str()).unwrap_or(Value::String(value));
+        obj.dot_set(dot_key.as_str(), new_value)
+            .map_err(|error| Error::EnvKeyError(key.clone(), error.to_string()))?;
     }
 
     Ok(obj)
@@ -703,81 +1073,129 @@ fn process_envs(option: EnvSourceOptions) -> Result<Value, Error> {
 ///
 /// # Returns
 ///
-/// An asynchronous task that resolves to a `serde_json::Value` representing the contents of the file.
-///
-/// # Panics
-///
-/// This function will panic if it is unable to open the file or if there is an error while reading from it.
+/// An asynchronous task that resolves to a `serde_json::Value` representing the contents of the file,
+/// or the error met while opening, reading or parsing it.
 #[cfg(any(feature = "tokio", feature = "async-std"))]
-async fn read_file_value(path: String) -> Value {
+async fn read_file_value(path: String) -> Result<Value, Error> {
     let mut contents = String::new();
 
     #[cfg(feature = "tokio")]
     {
         use tokio::io::AsyncReadExt;
-        let mut file = tokio::fs::File::open(path.clone())
-            .await
-            .expect("Unable to open file");
-        file.read_to_string(&mut contents).await.unwrap();
+        let mut file = tokio::fs::File::open(path.clone()).await?;
+        file.read_to_string(&mut contents).await?;
     }
 
     #[cfg(feature = "async-std")]
     {
         use async_std::io::ReadExt;
-        let mut file = async_std::fs::File::open(path.clone())
-            .await
-            .expect("Unable to open file");
-        file.read_to_string(&mut contents).await.unwrap();
+        let mut file = async_std::fs::File::open(path.clone()).await?;
+        file.read_to_string(&mut contents).await?;
     };
 
-    convert_str_to_value(path, contents)
+    parse_file_value(&path, &contents)
 }
 
-fn convert_str_to_value(path: String, contents: String) -> Value {
+/// Format of a config file according to its extension, JSON being the default
+fn file_format(path: &str) -> &'static str {
     if path.ends_with(".yaml") || path.ends_with(".yml") {
-        #[cfg(not(feature = "yaml"))]
-        {
-            panic!("missing yaml feature for crate, please enable yaml feature")
-        }
-
-        #[cfg(feature = "yaml")]
-        {
-            let yaml: Value = serde_yaml::from_str::<Value>(&contents)
-                .expect("YAML does not have correct format.");
-            yaml
-        }
+        "yaml"
     } else if path.ends_with(".toml") {
-        #[cfg(not(feature = "toml"))]
-        {
-            panic!("missing toml feature for crate, please enable toml feature")
-        }
+        "toml"
+    } else if path.ends_with(".ron") {
+        "ron"
+    } else {
+        "json"
+    }
+}
 
+/// Parses the contents of a config file according to its extension, JSON
+/// being the default
+fn parse_file_value(path: &str, contents: &str) -> Result<Value, Error> {
+    match file_format(path) {
+        #[cfg(feature = "yaml")]
+        "yaml" => Ok(serde_yaml::from_str::<Value>(contents)?),
         #[cfg(feature = "toml")]
-        {
-            let rsp: Value =
-                toml::from_str::<Value>(&contents).expect("TOML does not have correct format.");
-            rsp
-        }
-    } else if path.ends_with(".json") {
-        let json: Value =
-            serde_json::from_str(&contents).expect("JSON does not have correct format.");
-        json
-    } else if path.ends_with(".ron") {
-        #[cfg(not(feature = "ron"))]
-        {
-            panic!("missing ron feature for crate, please enable ron feature")
+        "toml" => Ok(toml::from_str::<Value>(contents)?),
+        #[cfg(feature = "ron")]
+        "ron" => {
+            ron::de::from_str::<Value>(contents).map_err(|error| Error::RonParseError(error.code))
         }
+        "json" => Ok(serde_json::from_str::<Value>(contents)?),
+        format => Err(Error::UnsupportedFormat(format!(
+            "missing {format} feature for crate, please enable {format} feature"
+        ))),
+    }
+}
 
-        #[cfg(feature = "ron")]
-        {
-            let data = ron::de::from_str(&contents).expect("RON does not have correct format.");
-            dbg!(contents.clone());
-            data
+/// Reads a config file on the current thread, e.g a watched one on the
+/// thread of its watcher
+fn read_file_value_blocking(path: &Path) -> Result<Value, Error> {
+    let file_path = path.display().to_string();
+    let contents = std::fs::read_to_string(path)?;
+    parse_file_value(&file_path, &contents).map_err(|error| {
+        error.in_source(
+            SourceName::File(file_path.clone()),
+            Some(file_format(&file_path)),
+        )
+    })
+}
+
+/// Reads a watched env file again, overriding the variables it sets, on the
+/// thread of its watcher
+fn reload_env_value(path: &Path, options: EnvSourceOptions) -> Result<Value, Error> {
+    dotenvy::from_path_override(path).map_err(|error| {
+        Error::from(error).in_source(SourceName::EnvFile(path.display().to_string()), Some("env"))
+    })?;
+    let prefix = env_prefix(&options);
+    process_envs(options).map_err(|error| error.in_source(SourceName::Env(prefix), Some("env")))
+}
+
+/// Watches the sources with `watch` enabled, reloading the config whenever
+/// they change. Watchers stop along with the config
+fn watch_sources<T>(config: &NorthConfig<T>, sources: Vec<ConfigSource>)
+where
+    T: Clone + de::DeserializeOwned + Send + Sync + 'static,
+{
+    let cargo_path = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| "./".to_string());
+    let is_release = !cfg!(debug_assertions);
+
+    for (index, source) in sources.into_iter().enumerate() {
+        let state = Arc::downgrade(&config.state);
+        let watcher = match source {
+            ConfigSource::File(original_path, Some(options)) if options.watch => {
+                let path = resolve_file_path(&cargo_path, &original_path, is_release, &options);
+                watch_file_path(&path.clone(), move || {
+                    if let Some(state) = state.upgrade() {
+                        state.reload_layer(index, read_file_value_blocking(&path));
+                    }
+                })
+            }
+            ConfigSource::Env(options) if options.watch => {
+                let file_path = options.env_file_path.clone();
+                let path = PathBuf::from(env_file_path(file_path.as_deref().unwrap_or("/.env")));
+                watch_file_path(&path.clone(), move || {
+                    if let Some(state) = state.upgrade() {
+                        state.reload_layer(index, reload_env_value(&path, options.clone()));
+                    }
+                })
+            }
+            #[cfg(feature = "consul")]
+            ConfigSource::Consul(consul_opt) if consul_opt.watch => {
+                if let Err(error) =
+                    crate::consul_source::spawn_consul_watch(&config.state, index, consul_opt)
+                {
+                    log::error!("Unable to watch config in Consul: {}", error);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+
+        match watcher {
+            Ok(watcher) => config.state.keep_watcher(watcher),
+            Err(error) => log::error!("Unable to watch config source: {}", error),
         }
-    } else {
-        let json: Value =
-            serde_json::from_str(&contents).expect("JSON does not have correct format.");
-        json
     }
 }
 
@@ -785,20 +1203,13 @@ fn convert_str_to_value(path: String, contents: String) -> Value {
 ///
 /// # Arguments
 ///
-/// * `path` - A `String` that represents the path of the file to be read.
-///
-/// # Panics
-///
-/// This function will panic if the file cannot be opened.
+/// * `path` - The path of the file to be read.
 ///
 /// # Returns
 ///
-/// A `Value` representing the contents of the file.
+/// A `Value` representing the contents of the file, or the error met while reading or parsing it.
 #[cfg(not(any(feature = "tokio", feature = "async-std")))]
-fn read_file_value(path: String) -> Value {
-    let mut contents = String::new();
-    let mut file = std::fs::File::open(path.clone()).expect("Unable to open file");
-    file.read_to_string(&mut contents).unwrap();
-
-    convert_str_to_value(path, contents)
+fn read_file_value(path: &Path) -> Result<Value, Error> {
+    let contents = std::fs::read_to_string(path)?;
+    parse_file_value(&path.display().to_string(), &contents)
 }
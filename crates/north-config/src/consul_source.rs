@@ -0,0 +1,363 @@
+use std::pin::Pin;
+use std::sync::Arc;
+
+use base64::Engine;
+use futures::{Stream, StreamExt};
+use json_dotpath::DotPaths;
+use north_consul::kv::{KVPair, KV};
+use north_consul::watch::{kv_get, kv_list, WatchOptions};
+use north_consul::{Client, QueryOptions};
+use serde::de;
+use serde_json::Value;
+use tokio::task::JoinHandle;
+
+use crate::config_source::ConfigState;
+use crate::error::{Error, SourceName};
+use crate::interpolate::escape_value;
+
+/// # ConsulFormat
+///
+/// How the configuration is laid out in Consul KV
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ConsulFormat {
+    /// Every key under the prefix is a field, nested on `/`, e.g
+    /// `config/billing/database/url` is `database.url` under the prefix
+    /// `config/billing`. Values are parsed as JSON when they can be, and
+    /// kept as strings otherwise
+    #[default]
+    Keys,
+
+    /// The key is a single JSON document
+    Json,
+
+    /// The key is a single YAML document, needs the `yaml` feature
+    Yaml,
+}
+
+/// # ConsulSourceOptions
+///
+/// struct exposes available options for configuring a Consul KV source.
+#[derive(Debug, Clone)]
+pub struct ConsulSourceOptions {
+    /// Address of the Consul agent
+    ///
+    /// @defaults to `CONSUL_HTTP_ADDR`, or ["http://127.0.0.1:8500"]
+    pub address: String,
+
+    /// ACL token sent along requests
+    ///
+    /// @defaults to `CONSUL_HTTP_TOKEN`
+    pub token: Option<String>,
+
+    /// Datacenter to read from, the one of the agent when not set
+    ///
+    /// @defaults to [None]
+    pub datacenter: Option<String>,
+
+    /// Key prefix holding the fields, or key of the document for the JSON
+    /// and YAML formats
+    ///
+    /// @defaults to ["config"]
+    pub prefix: String,
+
+    /// How the configuration is laid out under the prefix
+    ///
+    /// @defaults to [ConsulFormat::Keys]
+    pub format: ConsulFormat,
+
+    /// Reload the configuration when the keys change, through Consul
+    /// blocking queries
+    ///
+    /// @defaults to False
+    pub watch: bool,
+
+    /// Start without this source instead of failing when Consul can't be
+    /// reached or holds an invalid document. Reloads always keep the last
+    /// good value on errors
+    ///
+    /// @defaults to False
+    pub fail_open: bool,
+}
+
+impl Default for ConsulSourceOptions {
+    fn default() -> Self {
+        let address = match std::env::var("CONSUL_HTTP_ADDR") {
+            Ok(address) if address.starts_with("http") => address,
+            Ok(address) => format!("http://{}", address),
+            Err(_) => "http://127.0.0.1:8500".to_string(),
+        };
+
+        ConsulSourceOptions {
+            address,
+            token: std::env::var("CONSUL_HTTP_TOKEN").ok(),
+            datacenter: None,
+            prefix: "config".to_string(),
+            format: ConsulFormat::Keys,
+            watch: false,
+            fail_open: false,
+        }
+    }
+}
+
+type ValueStream = Pin<Box<dyn Stream<Item = Result<Value, Error>> + Send>>;
+
+fn new_client(options: &ConsulSourceOptions) -> Result<Client, Error> {
+    let mut config = north_consul::Config::new()?;
+    config.address = options.address.trim_end_matches('/').to_string();
+    config.token = options.token.clone();
+    config.datacenter = options.datacenter.clone();
+    Ok(Client::new(config))
+}
+
+fn decode(pair: &KVPair) -> Result<String, Error> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(&pair.Value)
+        .map_err(|e| Error::ConsulValueError(pair.Key.clone(), e.to_string()))?;
+    String::from_utf8(bytes).map_err(|e| Error::ConsulValueError(pair.Key.clone(), e.to_string()))
+}
+
+/// Folder holding the keys of a prefix, so `config/billing` doesn't list
+/// the keys of `config/billing-admin`
+fn keys_folder(prefix: &str) -> String {
+    match prefix.trim_end_matches('/') {
+        "" => String::new(),
+        prefix => format!("{prefix}/"),
+    }
+}
+
+/// Nests the keys under the prefix into a single object
+fn keys_to_value(prefix: &str, pairs: Vec<KVPair>) -> Result<Value, Error> {
+    let folder = keys_folder(prefix);
+    let mut obj = Value::Object(Default::default());
+
+    for pair in pairs {
+        let path = match pair.Key.strip_prefix(&folder) {
+            Some(path) => path.trim_matches('/'),
+            None => continue,
+        };
+        // folders have no value of their own
+        if path.is_empty() || pair.Key.ends_with('/') {
+            continue;
+        }
+
+        let raw = decode(&pair)?;
+        let value = serde_json::from_str::<Value>(&raw).unwrap_or(Value::String(raw));
+        obj.dot_set(&path.replace('/', "."), value)
+            .map_err(|e| Error::ConsulValueError(pair.Key.clone(), e.to_string()))?;
+    }
+
+    Ok(obj)
+}
+
+fn document_to_value(format: &ConsulFormat, pair: Option<KVPair>) -> Result<Value, Error> {
+    let pair = match pair {
+        Some(pair) => pair,
+        None => return Ok(Value::Null),
+    };
+    let raw = decode(&pair)?;
+
+    match format {
+        #[cfg(feature = "yaml")]
+        ConsulFormat::Yaml => Ok(serde_yaml::from_str::<Value>(&raw)?),
+        #[cfg(not(feature = "yaml"))]
+        ConsulFormat::Yaml => Err(Error::UnsupportedFormat(
+            "missing yaml feature for crate, please enable yaml feature".into(),
+        )),
+        _ => Ok(serde_json::from_str::<Value>(&raw)?),
+    }
+}
+
+/// Reads the configuration once
+async fn read_value(client: &Client, options: &ConsulSourceOptions) -> Result<Value, Error> {
+    let query = QueryOptions {
+        datacenter: options.datacenter.clone(),
+        ..Default::default()
+    };
+
+    let value = match options.format {
+        ConsulFormat::Keys => {
+            let folder = keys_folder(&options.prefix);
+            let (pairs, _) = client.list(&folder, Some(&query)).await?;
+            keys_to_value(&options.prefix, pairs)
+        }
+        _ => {
+            let (pair, _) = client.get(&options.prefix, Some(&query)).await?;
+            document_to_value(&options.format, pair)
+        }
+    };
+    // whoever writes the keys must not be able to read local files through
+    // placeholders
+    value.map(escape_value)
+}
+
+/// Streams the configuration every time it changes
+fn watch_value(client: Client, options: ConsulSourceOptions) -> ValueStream {
+    let watch_options = WatchOptions {
+        datacenter: options.datacenter.clone(),
+        ..Default::default()
+    };
+
+    match options.format {
+        ConsulFormat::Keys => {
+            let prefix = options.prefix.clone();
+            let stream = kv_list(client, &keys_folder(&prefix), watch_options);
+            Box::pin(stream.map(move |pairs| keys_to_value(&prefix, pairs?).map(escape_value)))
+        }
+        format => {
+            let stream = kv_get(client, &options.prefix, watch_options);
+            Box::pin(stream.map(move |pair| document_to_value(&format, pair?).map(escape_value)))
+        }
+    }
+}
+
+/// Resolves a Consul KV source to Serde [Value], skipped on errors with
+/// `fail_open`
+pub(crate) async fn resolve_consul_source(
+    options: &ConsulSourceOptions,
+) -> Result<Option<Value>, Error> {
+    let value = match new_client(options) {
+        Ok(client) => read_value(&client, options).await,
+        Err(error) => Err(error),
+    };
+    let value = match value {
+        Ok(value) if value.is_null() => Err(Error::ConsulValueError(
+            options.prefix.clone(),
+            "key not found".into(),
+        )),
+        value => value,
+    };
+
+    match value {
+        Ok(value) => Ok(Some(value)),
+        Err(error) if options.fail_open => {
+            log::error!(
+                "Config from Consul {} was not loaded: {}",
+                options.prefix,
+                error
+            );
+            Ok(None)
+        }
+        Err(error) => Err(error.in_source(SourceName::Consul(options.prefix.clone()), None)),
+    }
+}
+
+/// Reloads the source at `index` of the config every time its keys change,
+/// until the config is dropped
+pub(crate) fn spawn_consul_watch<T>(
+    state: &Arc<ConfigState<T>>,
+    index: usize,
+    options: ConsulSourceOptions,
+) -> Result<JoinHandle<()>, Error>
+where
+    T: Clone + de::DeserializeOwned + Send + Sync + 'static,
+{
+    let client = new_client(&options)?;
+    let state = Arc::downgrade(state);
+
+    Ok(tokio::spawn(async move {
+        let mut changes = watch_value(client, options.clone());
+        while let Some(change) = changes.next().await {
+            let state = match state.upgrade() {
+                Some(state) => state,
+                None => break,
+            };
+
+            let change = match change {
+                Ok(value) if value.is_null() => Err(Error::ConsulValueError(
+                    options.prefix.clone(),
+                    "key not found".into(),
+                )),
+                change => change,
+            };
+            let source = SourceName::Consul(options.prefix.clone());
+            state.reload_layer(index, change.map_err(|error| error.in_source(source, None)));
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(key: &str, value: &str) -> KVPair {
+        KVPair {
+            Key: key.to_string(),
+            Value: base64::engine::general_purpose::STANDARD.encode(value),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn nests_keys_under_the_prefix() {
+        let pairs = vec![
+            pair("config/billing/", ""),
+            pair("config/billing/host", "0.0.0.0"),
+            pair("config/billing/nested/foo", "consul foo"),
+            pair("config/billing/nested/port", "8080"),
+        ];
+
+        let value = keys_to_value("config/billing/", pairs).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "host": "0.0.0.0",
+                "nested": { "foo": "consul foo", "port": 8080 }
+            })
+        );
+    }
+
+    #[test]
+    fn parses_json_documents() {
+        let value = document_to_value(
+            &ConsulFormat::Json,
+            Some(pair("config/billing", r#"{"host": "consul"}"#)),
+        )
+        .unwrap();
+        assert_eq!(value, serde_json::json!({ "host": "consul" }));
+
+        let missing = document_to_value(&ConsulFormat::Json, None).unwrap();
+        assert!(missing.is_null());
+    }
+
+    #[test]
+    fn ignores_keys_of_sibling_prefixes() {
+        let pairs = vec![
+            pair("config/billing/host", "0.0.0.0"),
+            pair("config/billing-admin/host", "admin"),
+        ];
+
+        let value = keys_to_value("config/billing", pairs).unwrap();
+        assert_eq!(value, serde_json::json!({ "host": "0.0.0.0" }));
+        assert_eq!(keys_folder("config/billing"), "config/billing/");
+        assert_eq!(keys_folder(""), "");
+    }
+
+    #[tokio::test]
+    async fn reports_missing_documents() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // consul answers a missing key with a 404 and an empty body
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = [0; 1024];
+                let _ = socket.read(&mut request).await;
+                let response = "HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n";
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+
+        for format in [ConsulFormat::Json, ConsulFormat::Yaml] {
+            let options = ConsulSourceOptions {
+                address: format!("http://{address}"),
+                prefix: "config/billing".to_string(),
+                format,
+                ..Default::default()
+            };
+            let error = resolve_consul_source(&options).await.unwrap_err();
+            assert!(error.to_string().contains("key not found"), "{error}");
+        }
+    }
+}
@@ -8,17 +8,102 @@ use std::fmt::Display;
 ///
 /// - `IoError`: Represents an I/O error encountered while performing file I/O operations. It contains an `std::io::Error` as the inner error.
 /// - `JsonParseError`: Represents an error encountered while parsing JSON data. It contains a `serde_json::Error` as the inner error.
+/// - `EnvFileError`: Represents an error encountered while loading an env file. It contains a `dotenvy::Error` as the inner error.
+/// - `WatchError`: Represents an error encountered while watching a config file. It contains a `notify::Error` as the inner error.
 /// - `RonParseError`: Represents an error encountered while parsing RON data. This variant is only available if the `"ron"` feature is enabled. It contains
+/// - `ConsulError`: Represents an error returned by Consul, along with its causes. This variant is only available if the `"consul"` feature is enabled.
+/// - `ConsulValueError`: Represents a Consul KV key, and why its value could not be read. This variant is only available if the `"consul"` feature is enabled.
+/// - `FileNotFound`: Represents a config file path that does not exist.
+/// - `UnsupportedFormat`: Represents a file format that needs a crate feature that is not enabled.
+/// - `CustomSourceError`: Represents an error message of a custom source.
+/// - `EnvKeyError`: Represents an env variable, and why it could not be set at its key path.
+/// - `SourceError`: Represents an error of a single source, along with the source and its format.
+/// - `DeserializeError`: Represents the dotted key path of the merged config that failed to deserialize, with the expected and actual types when known.
+/// - `ReferenceError`: Represents a placeholder that could not be resolved, along with the key holding it and why.
+/// - `ReferenceCycle`: Represents keys whose placeholders reference each other, in the order they were followed.
+/// - `SchemaError`: Represents a JSON Schema of the config type that could not be compiled. This variant is only available if the `"schema"` feature is enabled.
+/// - `ValidationError`: Represents every key path of the merged config that violates the JSON Schema of its type, along with why. This variant is only available if the `"schema"` feature is enabled.
 #[derive(Debug)]
 pub enum Error {
     IoError(std::io::Error),
     JsonParseError(serde_json::Error),
+    EnvFileError(dotenvy::Error),
+    WatchError(notify::Error),
     #[cfg(feature = "ron")]
     RonParseError(ron::Error),
     #[cfg(feature = "yaml")]
     YamlParseError(serde_yaml::Error),
     #[cfg(feature = "toml")]
     TomlParseError(toml::de::Error),
+    #[cfg(feature = "consul")]
+    ConsulError(String),
+    #[cfg(feature = "consul")]
+    ConsulValueError(String, String),
+    FileNotFound(String),
+    UnsupportedFormat(String),
+    CustomSourceError(String),
+    EnvKeyError(String, String),
+    SourceError {
+        source: SourceName,
+        format: Option<String>,
+        error: Box<Error>,
+    },
+    DeserializeError {
+        path: String,
+        expected: Option<String>,
+        actual: Option<String>,
+        message: String,
+    },
+    ReferenceError {
+        key: String,
+        placeholder: String,
+        message: String,
+    },
+    ReferenceCycle(Vec<String>),
+    #[cfg(feature = "schema")]
+    SchemaError(String),
+    #[cfg(feature = "schema")]
+    ValidationError(Vec<(String, String)>),
+}
+
+/// # SourceName
+///
+/// Identifies the source an [Error::SourceError] comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceName {
+    /// path of a config file
+    File(String),
+    /// prefix of env variables
+    Env(String),
+    /// path of an env file
+    EnvFile(String),
+    /// name of a custom source
+    Custom(String),
+    /// key or prefix of a Consul KV source
+    Consul(String),
+}
+
+impl Display for SourceName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceName::File(path) => write!(f, "file \"{}\"", path),
+            SourceName::Env(prefix) => write!(f, "env prefix \"{}\"", prefix),
+            SourceName::EnvFile(path) => write!(f, "env file \"{}\"", path),
+            SourceName::Custom(name) => write!(f, "custom source \"{}\"", name),
+            SourceName::Consul(key) => write!(f, "consul key \"{}\"", key),
+        }
+    }
+}
+
+impl Error {
+    /// Attributes the error to the source it comes from
+    pub(crate) fn in_source(self, source: SourceName, format: Option<&str>) -> Error {
+        Error::SourceError {
+            source,
+            format: format.map(String::from),
+            error: Box::new(self),
+        }
+    }
 }
 
 impl Display for Error {
@@ -26,17 +111,76 @@ impl Display for Error {
         match self {
             Error::IoError(io_error) => write!(f, "{}", io_error),
             Error::JsonParseError(io_error) => write!(f, "{}", io_error),
+            Error::EnvFileError(error) => write!(f, "{}", error),
+            Error::WatchError(error) => write!(f, "{}", error),
             #[cfg(feature = "ron")]
             Error::RonParseError(error) => write!(f, "{}", error),
             #[cfg(feature = "yaml")]
             Error::YamlParseError(error) => write!(f, "{}", error),
             #[cfg(feature = "toml")]
             Error::TomlParseError(error) => write!(f, "{}", error),
+            #[cfg(feature = "consul")]
+            Error::ConsulError(error) => write!(f, "{}", error),
+            #[cfg(feature = "consul")]
+            Error::ConsulValueError(key, error) => {
+                write!(f, "invalid value for {}: {}", key, error)
+            }
+            Error::FileNotFound(path) => write!(f, "no file found in path: {}", path),
+            Error::UnsupportedFormat(error) => write!(f, "{}", error),
+            Error::CustomSourceError(error) => write!(f, "{}", error),
+            Error::EnvKeyError(key, error) => write!(f, "invalid env variable {}: {}", key, error),
+            Error::SourceError {
+                source,
+                format: Some(format),
+                error,
+            } => write!(f, "{} ({}): {}", source, format, error),
+            Error::SourceError { source, error, .. } => write!(f, "{}: {}", source, error),
+            Error::DeserializeError {
+                path,
+                expected: Some(expected),
+                actual: Some(actual),
+                ..
+            } => write!(
+                f,
+                "invalid config at \"{}\": expected {}, found {}",
+                path, expected, actual
+            ),
+            Error::DeserializeError { path, message, .. } => {
+                write!(f, "invalid config at \"{}\": {}", path, message)
+            }
+            Error::ReferenceError {
+                key,
+                placeholder,
+                message,
+            } => write!(
+                f,
+                "unable to resolve \"${{{}}}\" at \"{}\": {}",
+                placeholder, key, message
+            ),
+            Error::ReferenceCycle(keys) => write!(f, "reference cycle: {}", keys.join(" -> ")),
+            #[cfg(feature = "schema")]
+            Error::SchemaError(error) => write!(f, "invalid config schema: {}", error),
+            #[cfg(feature = "schema")]
+            Error::ValidationError(violations) => {
+                write!(f, "invalid config, {} violation(s):", violations.len())?;
+                for (path, message) in violations {
+                    let path = if path.is_empty() { "(root)" } else { path };
+                    write!(f, "\n  - {}: {}", path, message)?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::SourceError { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 impl From<serde_json::Error> for Error {
     fn from(e: serde_json::Error) -> Self {
@@ -65,6 +209,27 @@ impl From<toml::de::Error> for Error {
     }
 }
 
+#[cfg(feature = "consul")]
+impl From<north_consul::errors::Error> for Error {
+    fn from(e: north_consul::errors::Error) -> Self {
+        // error_chain errors aren't Sync, keep the whole chain as text instead
+        let causes: Vec<String> = e.iter().map(|cause| cause.to_string()).collect();
+        Error::ConsulError(causes.join(": "))
+    }
+}
+
+impl From<dotenvy::Error> for Error {
+    fn from(e: dotenvy::Error) -> Self {
+        Error::EnvFileError(e)
+    }
+}
+
+impl From<notify::Error> for Error {
+    fn from(e: notify::Error) -> Self {
+        Error::WatchError(e)
+    }
+}
+
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Self {
         Error::IoError(e)
@@ -1,8 +1,14 @@
 #![allow(dead_code)]
 extern crate core;
 
+mod coerce;
 mod config_source;
+#[cfg(feature = "consul")]
+mod consul_source;
 mod error;
+mod interpolate;
+#[cfg(feature = "schema")]
+mod schema;
 mod utils;
 
 pub mod serde_utils;
@@ -10,10 +16,20 @@ pub(crate) mod watcher;
 
 pub use {
     self::config_source::{
-        new_config, Case, ConfigSource, CustomConfigSource, EnvSourceOptions, FileSourceOptions,
-        NorthConfig, NorthConfigOptions,
+        new_config, try_new_config, Case, ConfigEvent, ConfigSource, CustomConfigSource,
+        EnvSourceOptions, FileSourceOptions, NorthConfig, NorthConfigOptions,
     },
-    self::error::Error,
+    self::error::{Error, SourceName},
+};
+
+#[cfg(feature = "consul")]
+pub use self::consul_source::{ConsulFormat, ConsulSourceOptions};
+
+#[cfg(feature = "schema")]
+pub use {
+    self::config_source::{new_validated_config, try_new_validated_config},
+    self::schema::{config_schema, write_config_schema, SchemaOptions, UnknownKeys},
+    schemars,
 };
 
 #[cfg(test)]
@@ -218,6 +234,7 @@ mod tests {
         destroy_env();
         envmnt::set("NORTH_HOST", "address");
         envmnt::set("NORTH_NESTED__FOO", "env foo");
+        envmnt::set("NORTH_NESTED__BAR", "env bar");
 
         let config_options = NorthConfigOptions {
             sources: vec![
@@ -240,6 +257,7 @@ mod tests {
 
         envmnt::remove("NORTH_HOST");
         envmnt::remove("NORTH_NESTED__FOO");
+        envmnt::remove("NORTH_NESTED__BAR");
         destroy_env();
     }
 
@@ -319,13 +337,189 @@ mod tests {
         envmnt::remove("NORTH_NAMES__0__FIRST");
     }
 
+    #[cfg(not(any(feature = "tokio", feature = "async-std")))]
+    #[test]
+    fn reloads_watched_files() {
+        use crate::{ConfigEvent, FileSourceOptions};
+        use std::time::Duration;
+
+        let dir = std::env::temp_dir().join(format!("north-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("reload.json");
+        std::fs::write(&path, r#"{"host": "first"}"#).unwrap();
+
+        let config_options = NorthConfigOptions {
+            sources: vec![ConfigSource::File(
+                path.display().to_string(),
+                Some(FileSourceOptions {
+                    watch: true,
+                    ..Default::default()
+                }),
+            )],
+        };
+        let config = crate::new_config::<SimpleDemoConfig>(config_options);
+        let changes = config.subscribe();
+
+        std::fs::write(&path, r#"{"host": "second"}"#).unwrap();
+        match changes.recv_timeout(Duration::from_secs(5)).unwrap() {
+            ConfigEvent::Changed { old, new } => {
+                assert_eq!(old.host.as_deref(), Some("first"));
+                assert_eq!(new.host.as_deref(), Some("second"));
+            }
+            ConfigEvent::ReloadFailed(error) => panic!("reload failed: {}", error),
+        }
+
+        std::fs::write(&path, r#"{"host": "#).unwrap();
+        loop {
+            match changes.recv_timeout(Duration::from_secs(5)).unwrap() {
+                ConfigEvent::ReloadFailed(_) => break,
+                ConfigEvent::Changed { .. } => continue,
+            }
+        }
+
+        assert_eq!(config.get_value().host.as_deref(), Some("first"));
+        assert_eq!(config.current_value().host.as_deref(), Some("second"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(not(any(feature = "tokio", feature = "async-std")))]
+    #[test]
+    fn names_the_source_and_key_that_failed() {
+        use crate::{CustomConfigSource, Error, SourceName};
+
+        let missing = NorthConfigOptions {
+            sources: vec![ConfigSource::File("missing.json".to_string(), None)],
+        };
+        match crate::try_new_config::<SimpleDemoConfig>(missing)
+            .err()
+            .unwrap()
+        {
+            Error::SourceError {
+                source: SourceName::File(path),
+                error,
+                ..
+            } => {
+                assert!(path.ends_with("missing.json"));
+                assert!(matches!(*error, Error::FileNotFound(_)));
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+
+        let dir = std::env::temp_dir().join(format!("north-config-errors-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("invalid.json");
+        std::fs::write(&path, r#"{"nested": {"foo": 1, "bar": "bar"}}"#).unwrap();
+        let invalid = NorthConfigOptions {
+            sources: vec![ConfigSource::File(path.display().to_string(), None)],
+        };
+        match crate::try_new_config::<DemoConfig>(invalid).err().unwrap() {
+            Error::DeserializeError {
+                path,
+                expected,
+                actual,
+                ..
+            } => {
+                assert_eq!(path, "nested.foo");
+                assert_eq!(expected.as_deref(), Some("a string"));
+                assert_eq!(actual.as_deref(), Some("integer `1`"));
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        #[derive(Clone, Debug)]
+        struct Unreachable;
+        impl CustomConfigSource for Unreachable {
+            fn get_config_value(&self) -> Result<serde_json::Value, Error> {
+                Err(Error::CustomSourceError("vault sealed".to_string()))
+            }
+            fn name(&self) -> String {
+                "vault".to_string()
+            }
+        }
+        let custom = NorthConfigOptions {
+            sources: vec![ConfigSource::Custom(Box::new(Unreachable))],
+        };
+        let error = crate::try_new_config::<SimpleDemoConfig>(custom)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("custom source \"vault\""));
+        assert!(error.to_string().contains("vault sealed"));
+    }
+
+    #[cfg(not(any(feature = "tokio", feature = "async-std")))]
+    #[test]
+    fn reads_secrets_and_env_placeholders_into_typed_fields() {
+        #[derive(Clone, serde::Deserialize, Debug)]
+        struct DbConfig {
+            pub password: String,
+            pub port: u16,
+        }
+
+        let dir = std::env::temp_dir().join(format!("north-config-secrets-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let secret = dir.join("db_password");
+        std::fs::write(&secret, "123456\n").unwrap();
+        let path = dir.join("db.json");
+        let contents = serde_json::json!({
+            "password": format!("${{file:{}}}", secret.display()),
+            "port": "${env:NORTH_CONFIG_UNSET_DB_PORT:-5432}",
+        });
+        std::fs::write(&path, contents.to_string()).unwrap();
+
+        let config_options = NorthConfigOptions {
+            sources: vec![ConfigSource::File(path.display().to_string(), None)],
+        };
+        let config = crate::try_new_config::<DbConfig>(config_options).unwrap();
+        assert_eq!(config.get_value().password, "123456");
+        assert_eq!(config.get_value().port, 5432);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(all(feature = "schema", not(any(feature = "tokio", feature = "async-std"))))]
+    #[test]
+    fn validates_config_against_its_schema() {
+        use crate::{Error, SchemaOptions, UnknownKeys};
+
+        #[derive(Clone, serde::Deserialize, schemars::JsonSchema, Debug)]
+        struct ServerConfig {
+            pub host: String,
+            pub port: u16,
+        }
+
+        let dir = std::env::temp_dir().join(format!("north-config-schema-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("server.json");
+        std::fs::write(&path, r#"{"host": 1, "port": -1, "hots": "typo"}"#).unwrap();
+
+        let config_options = NorthConfigOptions {
+            sources: vec![ConfigSource::File(path.display().to_string(), None)],
+        };
+        let schema_options = SchemaOptions {
+            unknown_keys: UnknownKeys::Deny,
+        };
+        match crate::try_new_validated_config::<ServerConfig>(config_options, schema_options)
+            .err()
+            .unwrap()
+        {
+            Error::ValidationError(violations) => {
+                let mut paths: Vec<&str> =
+                    violations.iter().map(|(path, _)| path.as_str()).collect();
+                paths.sort();
+                assert_eq!(paths, vec!["host", "hots", "port"]);
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[cfg(feature = "ron")]
     #[test]
     fn read_config_from_ron_source() {
         let config_options = NorthConfigOptions {
             sources: vec![ConfigSource::File(
                 "../../examples/configs/test.release.ron".to_string(),
-                None
+                None,
             )],
         };
         let config = crate::new_config::<DemoConfig>(config_options);
@@ -0,0 +1,276 @@
+use std::path::Path;
+
+use jsonschema::error::{TypeKind, ValidationErrorKind};
+use jsonschema::primitive_type::PrimitiveType;
+use jsonschema::JSONSchema;
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde_json::Value;
+
+use crate::config_source::Validator;
+use crate::error::Error;
+
+/// # UnknownKeys
+///
+/// How keys of the config that are not fields of the config type are reported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownKeys {
+    /// Unknown keys are ignored, as serde does
+    Allow,
+
+    /// Unknown keys are logged as warnings
+    #[default]
+    Warn,
+
+    /// Unknown keys are reported along with the other violations
+    Deny,
+}
+
+/// # SchemaOptions
+///
+/// struct exposes available options for validating the config against the
+/// JSON Schema of its type.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaOptions {
+    /// How keys that are not fields of the config type are reported
+    ///
+    /// @defaults to [UnknownKeys::Warn]
+    pub unknown_keys: UnknownKeys,
+}
+
+/// # config_schema
+///
+/// JSON Schema of the config type `T`, e.g for editors to autocomplete
+/// config files, see [write_config_schema]
+///
+/// Example
+/// ```rust
+/// #[derive(Clone, serde::Deserialize, schemars::JsonSchema)]
+/// struct DemoConfig {
+///     pub host: Option<String>,
+/// }
+///
+/// let schema = north_config::config_schema::<DemoConfig>();
+/// assert_eq!(schema["title"], "DemoConfig");
+/// assert!(schema["properties"]["host"].is_object());
+/// ```
+pub fn config_schema<T: JsonSchema>() -> Value {
+    let schema = SchemaSettings::draft07()
+        .into_generator()
+        .into_root_schema_for::<T>();
+    serde_json::to_value(schema).expect("JSON Schema is always valid JSON")
+}
+
+/// # write_config_schema
+///
+/// Writes the JSON Schema of the config type `T` to `path`. Editors using
+/// the YAML language server pick it up for a config file starting with
+/// `# yaml-language-server: $schema=<path>`
+pub fn write_config_schema<T: JsonSchema>(path: impl AsRef<Path>) -> Result<(), Error> {
+    let schema = serde_json::to_string_pretty(&config_schema::<T>())?;
+    std::fs::write(path, schema)?;
+    Ok(())
+}
+
+/// Builds the validator of the config type `T`, reporting every violation
+/// of the merged config at once
+pub(crate) fn schema_validator<T: JsonSchema>(options: &SchemaOptions) -> Result<Validator, Error> {
+    // a null field is the same as a missing one for serde, so nulls are
+    // removed before validating rather than allowed by the schema, which
+    // would hide the violations of optional objects behind an `anyOf`
+    let schema = SchemaSettings::draft07()
+        .with(|settings| settings.option_add_null_type = false)
+        .into_generator()
+        .into_root_schema_for::<T>();
+    let mut schema = serde_json::to_value(schema)?;
+    if options.unknown_keys != UnknownKeys::Allow {
+        deny_additional_properties(&mut schema);
+    }
+
+    let compiled =
+        JSONSchema::compile(&schema).map_err(|error| Error::SchemaError(error.to_string()))?;
+    let unknown_keys = options.unknown_keys;
+
+    Ok(Box::new(move |value: &Value| {
+        let value = without_nulls(value);
+        let errors = match compiled.validate(&value) {
+            Ok(()) => return Ok(()),
+            Err(errors) => errors,
+        };
+
+        let mut violations = Vec::new();
+        for error in errors {
+            let path = error.instance_path.clone().into_vec().join(".");
+            match error.kind {
+                ValidationErrorKind::AdditionalProperties { unexpected } => {
+                    for key in unexpected {
+                        let key = match path.is_empty() {
+                            true => key,
+                            false => format!("{}.{}", path, key),
+                        };
+                        match unknown_keys {
+                            UnknownKeys::Deny => violations.push((key, "unknown key".to_string())),
+                            _ => log::warn!("Unknown config key {}", key),
+                        }
+                    }
+                }
+                // deserialization parses these strings, e.g env placeholders
+                ValidationErrorKind::Type { kind } if coercible(&error.instance, &kind) => {}
+                _ => violations.push((path, error.to_string())),
+            }
+        }
+
+        match violations.is_empty() {
+            true => Ok(()),
+            false => Err(Error::ValidationError(violations)),
+        }
+    }))
+}
+
+/// Rejects the keys of objects that are not among their properties. Only
+/// subschemas are visited, so fields named e.g `properties` are left alone
+fn deny_additional_properties(schema: &mut Value) {
+    let fields = match schema {
+        Value::Object(fields) => fields,
+        _ => return,
+    };
+    if fields.contains_key("properties") && !fields.contains_key("additionalProperties") {
+        fields.insert("additionalProperties".to_string(), Value::Bool(false));
+    }
+
+    for (key, subschema) in fields.iter_mut() {
+        match (key.as_str(), subschema) {
+            ("properties" | "definitions", Value::Object(schemas)) => {
+                schemas.values_mut().for_each(deny_additional_properties)
+            }
+            ("items" | "allOf" | "anyOf" | "oneOf", Value::Array(schemas)) => {
+                schemas.iter_mut().for_each(deny_additional_properties)
+            }
+            ("items" | "additionalProperties", subschema) => deny_additional_properties(subschema),
+            _ => {}
+        }
+    }
+}
+
+/// Whether a string holds a value of the expected type, e.g `"8080"` where a
+/// number is expected, which deserialization accepts
+fn coercible(instance: &Value, kind: &TypeKind) -> bool {
+    let text = match instance {
+        Value::String(text) => text.trim(),
+        _ => return false,
+    };
+    let holds = |primitive: PrimitiveType| match primitive {
+        PrimitiveType::Integer => text.parse::<i64>().is_ok() || text.parse::<u64>().is_ok(),
+        PrimitiveType::Number => text.parse::<f64>().is_ok(),
+        PrimitiveType::Boolean => text.parse::<bool>().is_ok(),
+        _ => false,
+    };
+    match kind {
+        TypeKind::Single(primitive) => holds(*primitive),
+        TypeKind::Multiple(primitives) => (*primitives).into_iter().any(holds),
+    }
+}
+
+fn without_nulls(value: &Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .filter(|(_, field)| !field.is_null())
+                .map(|(name, field)| (name.clone(), without_nulls(field)))
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(without_nulls).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[allow(dead_code)]
+    #[derive(serde::Deserialize, JsonSchema)]
+    struct Nested {
+        pub port: u16,
+        pub foo: String,
+    }
+
+    #[allow(dead_code)]
+    #[derive(serde::Deserialize, JsonSchema)]
+    struct Demo {
+        pub host: Option<String>,
+        pub nested: Option<Nested>,
+    }
+
+    #[test]
+    fn reports_every_violation() {
+        let validate = schema_validator::<Demo>(&SchemaOptions {
+            unknown_keys: UnknownKeys::Deny,
+        })
+        .unwrap();
+
+        assert!(validate(&json!({ "host": null, "nested": { "port": 80, "foo": "foo" } })).is_ok());
+
+        // strings deserialization parses are accepted, e.g from env placeholders
+        assert!(validate(&json!({ "nested": { "port": "80", "foo": "foo" } })).is_ok());
+
+        let value = json!({ "host": 1, "nested": { "port": "eighty", "bar": "bar" } });
+        match validate(&value).err().unwrap() {
+            Error::ValidationError(violations) => {
+                let paths: Vec<&str> = violations.iter().map(|(path, _)| path.as_str()).collect();
+                assert_eq!(violations.len(), 4, "{:?}", violations);
+                assert!(paths.contains(&"host"));
+                assert!(paths.contains(&"nested.port"));
+                assert!(paths.contains(&"nested.bar"));
+                // missing required field
+                assert!(paths.contains(&"nested"));
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[allow(dead_code)]
+    #[derive(serde::Deserialize, JsonSchema)]
+    struct Plugin {
+        pub properties: std::collections::BTreeMap<String, String>,
+        pub items: Vec<Nested>,
+    }
+
+    #[test]
+    fn leaves_fields_named_after_schema_keywords_alone() {
+        let validate = schema_validator::<Plugin>(&SchemaOptions {
+            unknown_keys: UnknownKeys::Deny,
+        })
+        .unwrap();
+
+        let value = json!({ "properties": { "a": "b" }, "items": [{ "port": 80, "foo": "foo" }] });
+        assert!(validate(&value).is_ok());
+
+        let value = json!({
+            "properties": {},
+            "items": [{ "port": 80, "foo": "foo", "bar": "bar" }],
+            "additionalProperties": true,
+        });
+        match validate(&value).err().unwrap() {
+            Error::ValidationError(violations) => {
+                let mut paths: Vec<&str> =
+                    violations.iter().map(|(path, _)| path.as_str()).collect();
+                paths.sort();
+                assert_eq!(paths, vec!["additionalProperties", "items.0.bar"]);
+                assert!(violations.iter().all(|(_, reason)| reason == "unknown key"));
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+    }
+
+    #[test]
+    fn warns_about_unknown_keys_by_default() {
+        let validate = schema_validator::<Demo>(&SchemaOptions::default()).unwrap();
+        assert!(validate(&json!({ "hots": "typo" })).is_ok());
+
+        let schema = config_schema::<Demo>();
+        assert!(schema["definitions"]["Nested"].is_object());
+    }
+}
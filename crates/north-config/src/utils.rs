@@ -1,5 +1,7 @@
 use std::fs;
 
+use crate::error::{Error, SourceName};
+
 /// Set up the environment and logging configuration for the application.
 ///
 /// This function performs the following tasks:
@@ -7,14 +9,15 @@ use std::fs;
 /// - Sets the default value for the "RUST_LOG" environment variable if it is not already set.
 /// - Initializes the logging configuration for the application.
 /// ```
-pub(crate) fn preamble() {
-    import_env_vars("/.env");
+pub(crate) fn preamble() -> Result<(), Error> {
+    import_env_vars("/.env")?;
 
     if std::env::var_os("RUST_LOG").is_none() {
         std::env::set_var("RUST_LOG", "poem=debug");
     }
     // enable tracing
     // tracing_subscriber::fmt::init();
+    Ok(())
 }
 
 /// Imports environment variables from a file.
@@ -23,17 +26,25 @@ pub(crate) fn preamble() {
 ///
 /// * `file_path` - A string slice containing the path to the file to import.
 ///
-/// # Panics
+/// # Errors
 ///
-/// This function panics if the specified file does not exist or if there is an error while
-/// loading the environment variables from the file.
-pub(crate) fn import_env_vars(file_path: &str) {
-    let path_buf = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
-    let path = format!("{}/{}", path_buf, file_path);
+/// Returns an error naming the file when it exists but the environment variables can't be
+/// loaded from it. A missing file is skipped.
+pub(crate) fn import_env_vars(file_path: &str) -> Result<(), Error> {
+    let path = env_file_path(file_path);
 
     if path_exists(path.as_str()) {
-        dotenvy::from_path(path).unwrap();
+        dotenvy::from_path(&path).map_err(|error| {
+            Error::from(error).in_source(SourceName::EnvFile(path), Some("env"))
+        })?;
     }
+    Ok(())
+}
+
+/// Resolves an env file path against the crate directory
+pub(crate) fn env_file_path(file_path: &str) -> String {
+    let path_buf = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+    format!("{}/{}", path_buf, file_path)
 }
 
 /// Checks if a file or directory exists at the given path.
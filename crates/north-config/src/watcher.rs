@@ -1,15 +1,56 @@
-use notify::{RecursiveMode, Watcher};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use std::path::Path;
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::Duration;
 
-pub fn watch_file_path(path: Box<Path>) -> Result<(), crate::Error> {
-    // Automatically select the best implementation for your platform.
-    let mut watcher = notify::recommended_watcher(|res| match res {
-        Ok(event) => println!("event: {:?}", event),
-        Err(e) => println!("watch error: {:?}", e),
-    })
-    .unwrap();
+/// Time waited for writes to settle before reloading, so a file being
+/// truncated then written is read once, complete
+const DEBOUNCE: Duration = Duration::from_millis(100);
 
-    watcher.watch(&path, RecursiveMode::Recursive).unwrap();
+/// Calls `on_change` whenever the file at `path` is written or created, until
+/// the returned watcher is dropped.
+///
+/// The parent directory is watched rather than the file, so files replaced
+/// on save by editors, or created after the watch started, are still seen
+pub fn watch_file_path<F>(path: &Path, on_change: F) -> Result<RecommendedWatcher, crate::Error>
+where
+    F: Fn() + Send + 'static,
+{
+    let file_name = path.file_name().map(|name| name.to_os_string());
+    let (sender, receiver) = channel();
+    let mut watcher =
+        notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+            Ok(event) => {
+                let is_write = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+                let is_file = event.paths.iter().any(|changed| {
+                    changed.file_name().map(|name| name.to_os_string()) == file_name
+                });
+                if is_write && is_file {
+                    let _ = sender.send(());
+                }
+            }
+            Err(e) => log::error!("watch error: {:?}", e),
+        })?;
 
-    Ok(())
+    // the sender lives in the watcher, so the thread ends along with it
+    std::thread::spawn(move || {
+        while receiver.recv().is_ok() {
+            loop {
+                match receiver.recv_timeout(DEBOUNCE) {
+                    Ok(()) => continue,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            on_change();
+        }
+    });
+
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    watcher.watch(directory, RecursiveMode::NonRecursive)?;
+
+    Ok(watcher)
 }
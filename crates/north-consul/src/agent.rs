@@ -1,6 +1,7 @@
 use async_trait::async_trait;
 use std::collections::HashMap;
 
+use crate::catalog::Weights;
 use crate::errors::Result;
 use crate::request::{get, put};
 use crate::Client;
@@ -45,6 +46,8 @@ pub struct AgentService {
     pub EnableTagOverride: bool,
     pub CreateIndex: u64,
     pub ModifyIndex: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub Weights: Option<Weights>,
 }
 
 //I haven't implemetned https://www.consul.io/api/agent.html#read-configuration
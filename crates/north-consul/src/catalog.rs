@@ -6,7 +6,7 @@ use crate::errors::Result;
 use crate::request::{get, put};
 use crate::{Client, QueryMeta, QueryOptions, WriteMeta, WriteOptions};
 
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug, Clone)]
 #[serde(default)]
 pub struct Weights {
     pub Passing: u32,
@@ -0,0 +1,322 @@
+//! Leader election on top of Consul sessions and KV locks.
+//!
+//! Every candidate holds a session with a TTL and tries to acquire the
+//! election key with it. The candidate holding the key is the leader until it
+//! resigns or its session is invalidated, e.g because the process died and
+//! stopped renewing it. The `LockIndex` of the key grows on every acquisition
+//! and is handed to the leader as a fencing token.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use crate::errors::{Error, Result};
+use crate::kv::{KVPair, KV};
+use crate::session::{Session, SessionEntry};
+
+/// Options of a [LeaderElection]
+#[derive(Clone, Debug)]
+pub struct ElectionOptions {
+    /// KV key locked by the leader, e.g `service/billing/leader`
+    pub key: String,
+
+    /// Value stored under the key while leading, usually the instance ID
+    pub value: String,
+
+    /// Name of the Consul session
+    pub session_name: String,
+
+    /// TTL of the session, Consul accepts 10 seconds at least. A leader that
+    /// stops renewing its session loses leadership after at most twice the
+    /// TTL
+    ///
+    /// @defaults to 15 seconds
+    pub ttl: Duration,
+
+    /// Time during which the key can't be acquired again after the session
+    /// of the leader was invalidated
+    ///
+    /// @defaults to 1 second
+    pub lock_delay: Duration,
+
+    /// Interval between acquisition attempts while following, and first
+    /// backoff of [LeaderElection::run] after a failed campaign
+    ///
+    /// @defaults to 5 seconds
+    pub retry_interval: Duration,
+
+    /// Longest backoff of [LeaderElection::run] between failed campaigns
+    ///
+    /// @defaults to 1 minute
+    pub max_backoff: Duration,
+}
+
+impl ElectionOptions {
+    pub fn new(key: &str, value: &str) -> Self {
+        ElectionOptions {
+            key: key.to_string(),
+            value: value.to_string(),
+            session_name: format!("north-election-{key}"),
+            ttl: Duration::from_secs(15),
+            lock_delay: Duration::from_secs(1),
+            retry_interval: Duration::from_secs(5),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Leadership won by a [LeaderElection]. The session is renewed while a
+/// clone of it is alive, and destroyed once they are all dropped without
+/// resigning, so another instance can take over
+#[derive(Clone, Debug)]
+pub struct Leadership {
+    session: String,
+    token: u64,
+    leading: watch::Receiver<bool>,
+    state: Arc<watch::Sender<bool>>,
+}
+
+impl Leadership {
+    /// Grows on every change of leader. Pass it along with writes to shared
+    /// resources so they can reject writes from a former leader
+    pub fn fencing_token(&self) -> u64 {
+        self.token
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session
+    }
+
+    /// returns false once leadership was lost or given up
+    pub fn is_leader(&self) -> bool {
+        *self.leading.borrow()
+    }
+
+    /// resolves when leadership is lost or given up
+    pub async fn lost(&self) {
+        let mut leading = self.leading.clone();
+        while *leading.borrow() {
+            if leading.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    fn step_down(&self) {
+        let _ = self.state.send(false);
+    }
+}
+
+/// ## LeaderElection
+/// Elects a single leader among the instances sharing the election key
+///
+/// ```rust,ignore
+/// let election = LeaderElection::new(client, ElectionOptions::new("billing/leader", &instance_id));
+///
+/// // runs on one instance at a time, until `shutdown` resolves
+/// election
+///     .run(shutdown, |leadership| async move {
+///         loop {
+///             invoice(leadership.fencing_token()).await;
+///             tokio::time::sleep(Duration::from_secs(60)).await;
+///         }
+///     })
+///     .await?;
+/// ```
+pub struct LeaderElection<C> {
+    client: Arc<C>,
+    options: ElectionOptions,
+}
+
+impl<C> Clone for LeaderElection<C> {
+    fn clone(&self) -> Self {
+        LeaderElection {
+            client: self.client.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+impl<C> LeaderElection<C>
+where
+    C: Session + KV + Send + Sync + 'static,
+{
+    pub fn new(client: C, options: ElectionOptions) -> Self {
+        LeaderElection {
+            client: Arc::new(client),
+            options,
+        }
+    }
+
+    /// Waits until this instance becomes the leader
+    pub async fn campaign(&self) -> Result<Leadership> {
+        let mut session = self.create_session().await?;
+        loop {
+            if let Some(token) = self.try_acquire(&session).await? {
+                return Ok(self.lead(session, token));
+            }
+            tokio::time::sleep(self.options.retry_interval).await;
+
+            // a follower keeps its session alive too, a new one is created
+            // if it expired meanwhile
+            if !self.renew(&session).await {
+                session = self.create_session().await?;
+            }
+        }
+    }
+
+    /// Releases the key so another instance can take over right away, then
+    /// destroys the session
+    pub async fn resign(&self, leadership: &Leadership) -> Result<()> {
+        leadership.step_down();
+        let released = self
+            .client
+            .release(&self.pair(&leadership.session), None)
+            .await;
+        self.client.destroy(&leadership.session, None).await?;
+        released.map(|_| ())
+    }
+
+    /// Runs `job` whenever this instance leads, until `shutdown` resolves.
+    /// The job is dropped as soon as leadership is lost and started again
+    /// once it is won back. Leadership is given up when the job returns or
+    /// on shutdown. Failed campaigns, e.g while Consul is unreachable, are
+    /// logged and retried with a growing backoff.
+    pub async fn run<S, F, Fut>(&self, shutdown: S, mut job: F) -> Result<()>
+    where
+        S: Future<Output = ()>,
+        F: FnMut(Leadership) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        tokio::pin!(shutdown);
+        let mut backoff = self.options.retry_interval;
+        loop {
+            let leadership = tokio::select! {
+                leadership = self.campaign() => leadership,
+                _ = &mut shutdown => return Ok(()),
+            };
+            let leadership = match leadership {
+                Ok(leadership) => leadership,
+                Err(e) => {
+                    log::error!("campaign for {} failed: {e}", self.options.key);
+                    tokio::select! {
+                        _ = tokio::time::sleep(backoff) => {}
+                        _ = &mut shutdown => return Ok(()),
+                    }
+                    backoff = (backoff * 2).min(self.options.max_backoff);
+                    continue;
+                }
+            };
+            backoff = self.options.retry_interval;
+            log::info!(
+                "leading {} with fencing token {}",
+                self.options.key,
+                leadership.fencing_token()
+            );
+
+            tokio::select! {
+                _ = job(leadership.clone()) => return self.resign(&leadership).await,
+                _ = &mut shutdown => return self.resign(&leadership).await,
+                _ = leadership.lost() => {
+                    log::warn!("lost leadership of {}", self.options.key);
+                    let _ = self.client.destroy(&leadership.session, None).await;
+                }
+            }
+        }
+    }
+
+    fn pair(&self, session: &str) -> KVPair {
+        KVPair {
+            Key: self.options.key.clone(),
+            Value: self.options.value.clone(),
+            Session: Some(session.to_string()),
+            ..Default::default()
+        }
+    }
+
+    async fn create_session(&self) -> Result<String> {
+        let entry = SessionEntry {
+            Name: Some(self.options.session_name.clone()),
+            TTL: Some(format!("{}s", self.options.ttl.as_secs().max(10))),
+            Behavior: Some("release".to_string()),
+            LockDelay: Some(self.options.lock_delay.as_nanos() as u64),
+            ..Default::default()
+        };
+        let (session, _) = self.client.create(&entry, None).await?;
+        session
+            .ID
+            .ok_or_else(|| Error::from("Consul returned a session without ID"))
+    }
+
+    async fn renew(&self, session: &str) -> bool {
+        matches!(self.client.renew(session, None).await, Ok((entries, _)) if !entries.is_empty())
+    }
+
+    /// Returns the fencing token when the key was acquired
+    async fn try_acquire(&self, session: &str) -> Result<Option<u64>> {
+        let (acquired, _) = self.client.acquire(&self.pair(session), None).await?;
+        if !acquired {
+            return Ok(None);
+        }
+        let (pair, _) = self.client.get(&self.options.key, None).await?;
+        Ok(pair
+            .filter(|pair| pair.Session.as_deref() == Some(session))
+            .map(|pair| pair.LockIndex.unwrap_or_default()))
+    }
+
+    /// Keeps the session alive while leading, and steps down as soon as the
+    /// session can't be renewed or the key is held by another session. The
+    /// task only holds a weak reference to the state, so it ends once every
+    /// [Leadership] is dropped
+    fn lead(&self, session: String, token: u64) -> Leadership {
+        let (state, leading) = watch::channel(true);
+        let leadership = Leadership {
+            session,
+            token,
+            leading,
+            state: Arc::new(state),
+        };
+
+        let election = self.clone();
+        let session = leadership.session.clone();
+        let mut leading = leadership.leading.clone();
+        let state = Arc::downgrade(&leadership.state);
+        tokio::spawn(async move {
+            let interval = election.options.ttl / 3;
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(interval) => {}
+                    changed = leading.changed() => match changed {
+                        Ok(()) if *leading.borrow() => continue,
+                        // resigned or stepped down
+                        Ok(()) => return,
+                        // dropped without resigning
+                        Err(_) => {
+                            let _ = election.client.destroy(&session, None).await;
+                            return;
+                        }
+                    },
+                }
+                if !election.still_leading(&session).await {
+                    if let Some(state) = state.upgrade() {
+                        let _ = state.send(false);
+                    }
+                    return;
+                }
+            }
+        });
+        leadership
+    }
+
+    async fn still_leading(&self, session: &str) -> bool {
+        if !self.renew(session).await {
+            return false;
+        }
+        match self.client.get(&self.options.key, None).await {
+            Ok((pair, _)) => pair.and_then(|pair| pair.Session).as_deref() == Some(session),
+            Err(_) => false,
+        }
+    }
+}
@@ -6,7 +6,7 @@ use crate::errors::Result;
 use crate::request::get;
 use crate::{Client, QueryMeta, QueryOptions};
 
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(default)]
 pub struct HealthCheck {
     pub Node: String,
@@ -20,7 +20,7 @@ pub struct HealthCheck {
     pub ServiceTags: Option<Vec<String>>,
 }
 
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(default)]
 pub struct Node {
     pub ID: String,
@@ -33,7 +33,7 @@ pub struct Node {
     pub ModifyIndex: u64,
 }
 
-#[derive(Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
+#[derive(Clone, Eq, Default, PartialEq, Serialize, Deserialize, Debug)]
 #[serde(default)]
 pub struct ServiceEntry {
     pub Node: Node,
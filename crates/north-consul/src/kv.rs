@@ -3,7 +3,7 @@ use std::collections::HashMap;
 
 use crate::errors::Error;
 use crate::errors::Result;
-use crate::request::{delete, get, get_vec, put, put_body};
+use crate::request::{delete, get_vec, put, put_body};
 use crate::{Client, QueryMeta, QueryOptions, WriteMeta, WriteOptions};
 
 #[derive(Clone, Default, Eq, PartialEq, Serialize, Deserialize, Debug)]
@@ -59,8 +59,9 @@ impl KV for Client {
         options: Option<&QueryOptions>,
     ) -> Result<(Option<KVPair>, QueryMeta)> {
         let path = format!("/v1/kv/{}", key);
+        // consul answers a missing key with a 404, read as no pair
         let x: Result<(Vec<KVPair>, QueryMeta)> =
-            get(&path, &self.config, HashMap::new(), options).await;
+            get_vec(&path, &self.config, HashMap::new(), options).await;
         x.map(|r| (r.0.first().cloned(), r.1))
     }
 
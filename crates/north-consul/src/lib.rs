@@ -9,10 +9,12 @@ extern crate serde_derive;
 pub mod agent;
 pub mod catalog;
 pub mod connect_ca;
+pub mod election;
 pub mod errors;
 pub mod health;
 pub mod kv;
 pub mod session;
+pub mod watch;
 
 mod request;
 
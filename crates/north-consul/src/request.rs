@@ -50,9 +50,7 @@ pub async fn get_vec<R: DeserializeOwned>(
     let start = Instant::now();
     let request_builder = add_config_options(config.http_client.get(url), config);
     let result = request_builder.send().await;
-    let r = result
-        .chain_err(|| "HTTP request to consul failed")
-        .unwrap();
+    let r = result.chain_err(|| "HTTP request to consul failed")?;
 
     let x: Option<Result<u64>> =
         r.headers()
@@ -115,9 +113,7 @@ pub async fn get<R: DeserializeOwned>(
     let request_builder = add_config_options(config.http_client.get(url), config);
     let result = request_builder.send().await;
 
-    let r = result
-        .chain_err(|| "HTTP request to consul failed")
-        .unwrap();
+    let r = result.chain_err(|| "HTTP request to consul failed")?;
     let x: Option<Result<u64>> =
         r.headers()
             .get("X-Consul-Index")
@@ -128,7 +124,7 @@ pub async fn get<R: DeserializeOwned>(
                 Ok(u64::from_str(s).expect("Failed to parse valid number for last index"))
             });
 
-    let j = r.json().await.chain_err(|| "Failed to parse JSON").unwrap();
+    let j = r.json().await.chain_err(|| "Failed to parse JSON")?;
     let fx = match x {
         Some(r) => (j, Some(r?)),
         None => (j, None),
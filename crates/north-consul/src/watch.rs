@@ -0,0 +1,220 @@
+//! Watches on top of Consul blocking queries.
+//!
+//! A watch repeats a blocking query with the index of the previous response,
+//! so Consul holds the request until the result changes or the wait time
+//! elapses, and yields every result that differs from the previous one. The
+//! index handling, error backoff and rate limiting follow
+//! <https://developer.hashicorp.com/consul/api-docs/features/blocking>
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream::{self, Stream};
+use tokio::time::{sleep_until, Instant};
+
+use crate::catalog::Catalog;
+use crate::errors::Result;
+use crate::health::{Health, ServiceEntry};
+use crate::kv::{KVPair, KV};
+use crate::{QueryMeta, QueryOptions};
+
+/// Options of a watch
+#[derive(Clone, Debug)]
+pub struct WatchOptions {
+    /// Datacenter to query, the one of the client config when not set
+    pub datacenter: Option<String>,
+
+    /// Time Consul holds a blocking query when nothing changes, capped by
+    /// Consul at 10 minutes
+    ///
+    /// @defaults to 5 minutes
+    pub wait_time: Duration,
+
+    /// Minimum time between two queries, so a result changing very often
+    /// doesn't turn the watch into a busy loop
+    ///
+    /// @defaults to 1 second
+    pub min_interval: Duration,
+
+    /// Time waited before querying again after the first failed query,
+    /// doubled on every consecutive failure
+    ///
+    /// @defaults to 1 second
+    pub initial_backoff: Duration,
+
+    /// Upper bound of the time waited after failed queries
+    ///
+    /// @defaults to 60 seconds
+    pub max_backoff: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            datacenter: None,
+            wait_time: Duration::from_secs(300),
+            min_interval: Duration::from_secs(1),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+impl WatchOptions {
+    fn backoff(&self, failures: u32) -> Duration {
+        let factor = 2u32.saturating_pow(failures.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+struct WatchState<T, F> {
+    options: WatchOptions,
+    query: F,
+    index: u64,
+    last: Option<T>,
+    failures: u32,
+    next_query: Option<Instant>,
+}
+
+/// Turns a blocking query into a stream of its changed results.
+///
+/// `query` is called with the [QueryOptions] to send, the first result is
+/// always yielded and later ones only when they differ from the previous
+/// one. Failed queries are yielded as errors and retried with backoff, so
+/// the stream never ends by itself
+pub fn watch<T, F, Fut>(options: WatchOptions, query: F) -> impl Stream<Item = Result<T>>
+where
+    T: PartialEq + Clone,
+    F: FnMut(QueryOptions) -> Fut,
+    Fut: Future<Output = Result<(T, QueryMeta)>>,
+{
+    let state = WatchState {
+        options,
+        query,
+        index: 0,
+        last: None,
+        failures: 0,
+        next_query: None,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(next_query) = state.next_query {
+                sleep_until(next_query).await;
+            }
+            let started = Instant::now();
+            let query_options = QueryOptions {
+                datacenter: state.options.datacenter.clone(),
+                wait_index: (state.index > 0).then_some(state.index),
+                wait_time: Some(state.options.wait_time),
+            };
+
+            let (value, meta) = match (state.query)(query_options).await {
+                Ok(response) => response,
+                Err(error) => {
+                    state.failures = state.failures.saturating_add(1);
+                    state.next_query = Some(started + state.options.backoff(state.failures));
+                    return Some((Err(error), state));
+                }
+            };
+            state.failures = 0;
+            state.next_query = Some(started + state.options.min_interval);
+
+            let index = meta.last_index.unwrap_or(0);
+            state.index = if index < state.index {
+                // the index went backwards, e.g after a snapshot restore
+                0
+            } else {
+                // an index of 0 would make the next query return immediately
+                index.max(1)
+            };
+
+            if state.last.as_ref() != Some(&value) {
+                state.last = Some(value.clone());
+                return Some((Ok(value), state));
+            }
+        }
+    })
+}
+
+/// Watches the instances of a service, see [Health::service]
+pub fn health_service<C>(
+    client: C,
+    service: &str,
+    tag: Option<&str>,
+    passing_only: bool,
+    options: WatchOptions,
+) -> impl Stream<Item = Result<Vec<ServiceEntry>>>
+where
+    C: Health + Send + Sync + 'static,
+{
+    let client = Arc::new(client);
+    let service = service.to_string();
+    let tag = tag.map(String::from);
+    watch(options, move |query| {
+        let client = client.clone();
+        let service = service.clone();
+        let tag = tag.clone();
+        async move {
+            client
+                .service(&service, tag.as_deref(), passing_only, Some(&query))
+                .await
+        }
+    })
+}
+
+/// Watches the services of the catalog and their tags, see
+/// [Catalog::services]
+pub fn catalog_services<C>(
+    client: C,
+    options: WatchOptions,
+) -> impl Stream<Item = Result<HashMap<String, Vec<String>>>>
+where
+    C: Catalog + Send + Sync + 'static,
+{
+    let client = Arc::new(client);
+    watch(options, move |query| {
+        let client = client.clone();
+        async move { client.services(Some(&query)).await }
+    })
+}
+
+/// Watches a KV key, yielding `None` while it doesn't exist, see [KV::get]
+pub fn kv_get<C>(
+    client: C,
+    key: &str,
+    options: WatchOptions,
+) -> impl Stream<Item = Result<Option<KVPair>>>
+where
+    C: KV + Send + Sync + 'static,
+{
+    let client = Arc::new(client);
+    let key = key.to_string();
+    watch(options, move |query| {
+        let client = client.clone();
+        let key = key.clone();
+        async move { client.get(&key, Some(&query)).await }
+    })
+}
+
+/// Watches the KV keys under a prefix, see [KV::list]
+pub fn kv_list<C>(
+    client: C,
+    prefix: &str,
+    options: WatchOptions,
+) -> impl Stream<Item = Result<Vec<KVPair>>>
+where
+    C: KV + Send + Sync + 'static,
+{
+    let client = Arc::new(client);
+    let prefix = prefix.to_string();
+    watch(options, move |query| {
+        let client = client.clone();
+        let prefix = prefix.clone();
+        async move { client.list(&prefix, Some(&query)).await }
+    })
+}
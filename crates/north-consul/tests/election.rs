@@ -0,0 +1,340 @@
+extern crate north_consul;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use north_consul::election::{ElectionOptions, LeaderElection, Leadership};
+use north_consul::errors::{Error, Result};
+use north_consul::kv::{KVPair, KV};
+use north_consul::session::{Session, SessionEntry};
+use north_consul::{Client, Config, QueryMeta, QueryOptions, WriteMeta, WriteOptions};
+
+/// In-process stand-in for the session and KV endpoints used by elections
+#[derive(Clone, Default)]
+struct FakeConsul {
+    state: Arc<Mutex<FakeState>>,
+}
+
+#[derive(Default)]
+struct FakeState {
+    next_session: u64,
+    sessions: HashMap<String, SessionEntry>,
+    renewals: usize,
+    /// session creations failing before Consul "comes back"
+    failing_creates: usize,
+    kv: HashMap<String, KVPair>,
+}
+
+impl FakeConsul {
+    /// Invalidates a session as Consul does when its TTL expires
+    fn expire(&self, session: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.sessions.remove(session);
+        release_locks(&mut state, session);
+    }
+}
+
+fn release_locks(state: &mut FakeState, session: &str) {
+    for pair in state.kv.values_mut() {
+        if pair.Session.as_deref() == Some(session) {
+            pair.Session = None;
+        }
+    }
+}
+
+fn write_meta() -> WriteMeta {
+    WriteMeta {
+        request_time: Duration::default(),
+    }
+}
+
+fn query_meta() -> QueryMeta {
+    QueryMeta {
+        last_index: None,
+        request_time: Duration::default(),
+    }
+}
+
+#[async_trait]
+impl Session for FakeConsul {
+    async fn create(
+        &self,
+        session: &SessionEntry,
+        _: Option<&WriteOptions>,
+    ) -> Result<(SessionEntry, WriteMeta)> {
+        let mut state = self.state.lock().unwrap();
+        if state.failing_creates > 0 {
+            state.failing_creates -= 1;
+            return Err(Error::from("connection refused"));
+        }
+        state.next_session += 1;
+        let entry = SessionEntry {
+            ID: Some(format!("session-{}", state.next_session)),
+            ..session.clone()
+        };
+        state
+            .sessions
+            .insert(entry.ID.clone().unwrap(), entry.clone());
+        Ok((entry, write_meta()))
+    }
+
+    async fn destroy(&self, id: &str, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        self.expire(id);
+        Ok((true, write_meta()))
+    }
+
+    async fn info(
+        &self,
+        id: &str,
+        _: Option<&QueryOptions>,
+    ) -> Result<(Vec<SessionEntry>, QueryMeta)> {
+        let state = self.state.lock().unwrap();
+        Ok((
+            state.sessions.get(id).cloned().into_iter().collect(),
+            query_meta(),
+        ))
+    }
+
+    async fn list(&self, _: Option<&QueryOptions>) -> Result<(Vec<SessionEntry>, QueryMeta)> {
+        let state = self.state.lock().unwrap();
+        Ok((state.sessions.values().cloned().collect(), query_meta()))
+    }
+
+    async fn node(
+        &self,
+        _: &str,
+        options: Option<&QueryOptions>,
+    ) -> Result<(Vec<SessionEntry>, QueryMeta)> {
+        Session::list(self, options).await
+    }
+
+    async fn renew(
+        &self,
+        id: &str,
+        _: Option<&WriteOptions>,
+    ) -> Result<(Vec<SessionEntry>, WriteMeta)> {
+        let mut state = self.state.lock().unwrap();
+        state.renewals += 1;
+        match state.sessions.get(id) {
+            Some(entry) => Ok((vec![entry.clone()], write_meta())),
+            None => Err(Error::from(format!("Session id '{id}' not found"))),
+        }
+    }
+}
+
+#[async_trait]
+impl KV for FakeConsul {
+    async fn acquire(&self, pair: &KVPair, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        let mut state = self.state.lock().unwrap();
+        let session = pair.Session.clone().unwrap();
+        if !state.sessions.contains_key(&session) {
+            return Err(Error::from("invalid session"));
+        }
+        let current = state.kv.entry(pair.Key.clone()).or_insert_with(|| KVPair {
+            Key: pair.Key.clone(),
+            LockIndex: Some(0),
+            ..Default::default()
+        });
+        match current.Session.as_deref() {
+            Some(holder) => Ok((holder == session, write_meta())),
+            None => {
+                current.Session = Some(session);
+                current.Value = pair.Value.clone();
+                current.LockIndex = Some(current.LockIndex.unwrap_or_default() + 1);
+                Ok((true, write_meta()))
+            }
+        }
+    }
+
+    async fn delete(&self, key: &str, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        self.state.lock().unwrap().kv.remove(key);
+        Ok((true, write_meta()))
+    }
+
+    async fn get(
+        &self,
+        key: &str,
+        _: Option<&QueryOptions>,
+    ) -> Result<(Option<KVPair>, QueryMeta)> {
+        let state = self.state.lock().unwrap();
+        Ok((state.kv.get(key).cloned(), query_meta()))
+    }
+
+    async fn list(
+        &self,
+        prefix: &str,
+        _: Option<&QueryOptions>,
+    ) -> Result<(Vec<KVPair>, QueryMeta)> {
+        let state = self.state.lock().unwrap();
+        let pairs = state
+            .kv
+            .values()
+            .filter(|pair| pair.Key.starts_with(prefix))
+            .cloned()
+            .collect();
+        Ok((pairs, query_meta()))
+    }
+
+    async fn put(&self, pair: &KVPair, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        let mut state = self.state.lock().unwrap();
+        state.kv.insert(pair.Key.clone(), pair.clone());
+        Ok((true, write_meta()))
+    }
+
+    async fn release(&self, pair: &KVPair, _: Option<&WriteOptions>) -> Result<(bool, WriteMeta)> {
+        let mut state = self.state.lock().unwrap();
+        let released = match state.kv.get_mut(&pair.Key) {
+            Some(current) if current.Session == pair.Session => {
+                current.Session = None;
+                true
+            }
+            _ => false,
+        };
+        Ok((released, write_meta()))
+    }
+}
+
+fn options(instance: &str) -> ElectionOptions {
+    ElectionOptions {
+        ttl: Duration::from_millis(60),
+        retry_interval: Duration::from_millis(10),
+        ..ElectionOptions::new("billing/leader", instance)
+    }
+}
+
+async fn within<T>(future: impl std::future::Future<Output = T>) -> T {
+    tokio::time::timeout(Duration::from_secs(2), future)
+        .await
+        .expect("timed out")
+}
+
+#[tokio::test]
+async fn election_hands_over_when_the_leader_resigns() {
+    let consul = FakeConsul::default();
+    let first = LeaderElection::new(consul.clone(), options("a"));
+    let second = LeaderElection::new(consul.clone(), options("b"));
+
+    let leader = within(first.campaign()).await.unwrap();
+    let follower = tokio::spawn(async move { second.campaign().await });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!follower.is_finished());
+
+    within(first.resign(&leader)).await.unwrap();
+    let next: Leadership = within(follower).await.unwrap().unwrap();
+
+    assert!(!leader.is_leader());
+    assert!(next.is_leader());
+    assert!(next.fencing_token() > leader.fencing_token());
+}
+
+#[tokio::test]
+async fn election_steps_down_when_the_session_expires() {
+    let consul = FakeConsul::default();
+    let first = LeaderElection::new(consul.clone(), options("a"));
+    let second = LeaderElection::new(consul.clone(), options("b"));
+
+    let leader = within(first.campaign()).await.unwrap();
+    consul.expire(leader.session_id());
+    within(leader.lost()).await;
+
+    let next = within(second.campaign()).await.unwrap();
+    assert!(next.fencing_token() > leader.fencing_token());
+}
+
+#[tokio::test]
+async fn election_runs_the_job_on_one_instance_at_a_time() {
+    let consul = FakeConsul::default();
+    let running = Arc::new(Mutex::new(Vec::<(String, Instant)>::new()));
+
+    let candidates = ["a", "b", "c"].map(|instance| {
+        let election = LeaderElection::new(consul.clone(), options(instance));
+        let running = running.clone();
+        tokio::spawn(async move {
+            election
+                .run(tokio::time::sleep(Duration::from_millis(300)), |_| {
+                    let running = running.clone();
+                    async move {
+                        running
+                            .lock()
+                            .unwrap()
+                            .push((instance.to_string(), Instant::now()));
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+                    }
+                })
+                .await
+        })
+    });
+    for candidate in candidates {
+        within(candidate).await.unwrap().unwrap();
+    }
+
+    // every run lasts 50ms, so consecutive runs never start closer than that
+    let mut runs = running.lock().unwrap().clone();
+    runs.sort_by_key(|(_, started)| *started);
+    assert!(runs.len() >= 2);
+    for pair in runs.windows(2) {
+        assert!(pair[1].1 - pair[0].1 >= Duration::from_millis(45));
+    }
+}
+
+#[tokio::test]
+async fn election_gives_up_a_dropped_leadership() {
+    let consul = FakeConsul::default();
+    let first = LeaderElection::new(consul.clone(), options("a"));
+    let second = LeaderElection::new(consul.clone(), options("b"));
+
+    let leader = within(first.campaign()).await.unwrap();
+    let session = leader.session_id().to_string();
+    drop(leader);
+
+    let next = within(second.campaign()).await.unwrap();
+    assert_ne!(next.session_id(), session);
+    assert!(!consul.state.lock().unwrap().sessions.contains_key(&session));
+
+    // only the session of the new leader is renewed from now on
+    second.resign(&next).await.unwrap();
+    let renewals = consul.state.lock().unwrap().renewals;
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(consul.state.lock().unwrap().renewals, renewals);
+}
+
+#[tokio::test]
+async fn election_retries_failed_campaigns() {
+    let consul = FakeConsul::default();
+    consul.state.lock().unwrap().failing_creates = 2;
+    let election = LeaderElection::new(consul.clone(), options("a"));
+
+    let (led, mut leading) = tokio::sync::mpsc::channel(1);
+    let run = tokio::spawn(async move {
+        election
+            .run(tokio::time::sleep(Duration::from_millis(300)), move |_| {
+                let led = led.clone();
+                async move {
+                    let _ = led.send(()).await;
+                    std::future::pending::<()>().await
+                }
+            })
+            .await
+    });
+
+    within(leading.recv()).await.unwrap();
+    assert_eq!(consul.state.lock().unwrap().failing_creates, 0);
+    within(run).await.unwrap().unwrap();
+}
+
+#[ignore]
+#[tokio::test]
+async fn election_against_a_consul_agent() {
+    let config = Config::new().unwrap();
+    let first = LeaderElection::new(Client::new(config.clone()), options("a"));
+    let second = LeaderElection::new(Client::new(config), options("b"));
+
+    let leader = first.campaign().await.unwrap();
+    first.resign(&leader).await.unwrap();
+    let next = second.campaign().await.unwrap();
+
+    assert!(next.fencing_token() > leader.fencing_token());
+    second.resign(&next).await.unwrap();
+}
@@ -0,0 +1,112 @@
+extern crate north_consul;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::StreamExt;
+use north_consul::errors::Result;
+use north_consul::watch::{watch, WatchOptions};
+use north_consul::{QueryMeta, QueryOptions};
+
+fn options() -> WatchOptions {
+    WatchOptions {
+        min_interval: Duration::from_millis(1),
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(5),
+        ..Default::default()
+    }
+}
+
+fn meta(index: u64) -> QueryMeta {
+    QueryMeta {
+        last_index: Some(index),
+        request_time: Duration::from_millis(1),
+    }
+}
+
+#[tokio::test]
+async fn watch_yields_changes_and_tracks_the_index() {
+    let responses: VecDeque<Result<(&str, QueryMeta)>> = VecDeque::from(vec![
+        Ok(("a", meta(5))),
+        Ok(("a", meta(5))),
+        Ok(("b", meta(7))),
+        // index went backwards, queried again without index
+        Ok(("b", meta(3))),
+        Err("consul unavailable".into()),
+        Ok(("c", meta(0))),
+        Ok(("d", meta(9))),
+    ]);
+    let responses = Arc::new(Mutex::new(responses));
+    let indexes = Arc::new(Mutex::new(Vec::new()));
+
+    let stream = {
+        let indexes = indexes.clone();
+        watch(options(), move |query: QueryOptions| {
+            indexes.lock().unwrap().push(query.wait_index);
+            let response = responses.lock().unwrap().pop_front().unwrap();
+            async move { response }
+        })
+    };
+    let results: Vec<Result<&str>> = stream.take(5).collect().await;
+
+    let values: Vec<Option<&str>> = results.iter().map(|r| r.as_ref().ok().copied()).collect();
+    assert_eq!(
+        values,
+        vec![Some("a"), Some("b"), None, Some("c"), Some("d")]
+    );
+    assert_eq!(
+        *indexes.lock().unwrap(),
+        vec![None, Some(5), Some(5), Some(7), None, None, Some(1)]
+    );
+}
+
+#[ignore]
+#[tokio::test]
+async fn watch_kv_test() {
+    use north_consul::kv::{KVPair, KV};
+    use north_consul::watch::kv_get;
+    use north_consul::{Client, Config};
+
+    let client = Client::new(Config::new().unwrap());
+    let mut stream = Box::pin(kv_get(client.clone(), "watchkey", WatchOptions::default()));
+    assert_eq!(stream.next().await.unwrap().unwrap(), None);
+
+    let pair = KVPair {
+        Key: String::from("watchkey"),
+        Value: String::from("watchvalue"),
+        ..Default::default()
+    };
+    assert!(client.put(&pair, None).await.unwrap().0);
+    let watched = stream.next().await.unwrap().unwrap().unwrap();
+    assert_eq!(watched.Key, "watchkey");
+
+    client.delete("watchkey", None).await.unwrap();
+}
+
+#[tokio::test]
+async fn watch_kv_yields_none_for_a_missing_key() {
+    use north_consul::watch::kv_get;
+    use north_consul::{Client, Config};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // consul answers a missing key with a 404 and an empty body
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = socket.read(&mut request).await;
+            let response =
+                "HTTP/1.1 404 Not Found\r\nX-Consul-Index: 3\r\ncontent-length: 0\r\n\r\n";
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+
+    let config = Config {
+        address: format!("http://{address}"),
+        ..Config::new().unwrap()
+    };
+    let mut stream = Box::pin(kv_get(Client::new(config), "missing", options()));
+    assert_eq!(stream.next().await.unwrap().unwrap(), None);
+}
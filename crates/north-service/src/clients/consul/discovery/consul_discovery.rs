@@ -44,13 +44,20 @@ impl ConsulDiscoveryClient {
                 service_node.Node.Address.clone()
             };
 
-            let meta = service_node.Node.Meta.unwrap_or_default();
+            let mut meta = service_node.Node.Meta.unwrap_or_default();
             let secure = match meta.clone().contains_key("secure") {
                 true => meta.get("secure").unwrap().parse().unwrap(),
                 false => false,
             };
 
             let status = service_node.Checks[0].Status.clone();
+            if let Some(weights) = &service_node.Service.Weights {
+                let weight = match status.as_str() {
+                    "warning" => weights.Warning,
+                    _ => weights.Passing,
+                };
+                meta.insert(WEIGHT_METADATA_KEY.to_string(), weight.to_string());
+            }
 
             let opts = ServiceInstanceOptions {
                 instance_id: service_node.Service.ID.clone(),
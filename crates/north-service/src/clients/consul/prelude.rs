@@ -23,6 +23,7 @@ pub(crate) use north_consul::health::{Health, ServiceEntry};
 
 pub(crate) use north_common::discovery::discovery_client::DiscoveryClient;
 pub(crate) use north_common::error::Error;
+pub(crate) use north_common::loadbalancer::weighted_strategy::WEIGHT_METADATA_KEY;
 pub(crate) use north_common::registry::default_service_instance::DefaultServiceInstance;
 pub(crate) use north_common::registry::service_instance::ServiceInstanceOptions;
 
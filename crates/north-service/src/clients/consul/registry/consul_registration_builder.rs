@@ -150,6 +150,7 @@ impl RegistrationBuilder<ConsulRegistration, CatalogRegistration> for ConsulRegi
                 EnableTagOverride: false,
                 CreateIndex: 0,
                 ModifyIndex: 0,
+                Weights: None,
             }),
             Check: Option::from(AgentCheck {
                 Node: "".to_string(),
@@ -5,3 +5,4 @@ pub mod consul;
 pub mod mdns;
 
 pub mod base_client;
+pub mod north_client;
@@ -0,0 +1,575 @@
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use north_common::discovery::discovery_client::DiscoveryClient;
+use north_common::error::Error;
+use north_common::loadbalancer::base_strategy::BaseStrategy;
+use north_common::loadbalancer::round_robin_strategy::RoundRobinStrategy;
+use north_common::loadbalancer::service_pool::{PoolSnapshot, ServicePool, ServicePoolOptions};
+use north_common::registry::service_instance::{PooledServiceInstance, ServiceInstance};
+use north_common::registry::service_instance_state::ServiceInstanceState;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, Response, StatusCode, Url};
+
+/// Headers copied from the incoming request by [NorthRequest::propagate]
+pub const PROPAGATED_HEADERS: [&str; 8] = [
+    "x-request-id",
+    "traceparent",
+    "tracestate",
+    "b3",
+    "x-b3-traceid",
+    "x-b3-spanid",
+    "x-b3-parentspanid",
+    "x-b3-sampled",
+];
+
+/// ## NorthClientOptions
+/// Timeouts and retries of a [NorthClient]
+#[derive(Clone, Debug)]
+pub struct NorthClientOptions {
+    /// Timeout of a single attempt
+    ///
+    /// @defaults to 10 seconds
+    pub timeout: Duration,
+
+    /// Attempts made after the first one failed. Every retry goes to
+    /// another instance of the service when there is one
+    ///
+    /// @defaults to 2
+    pub retries: u32,
+
+    /// Delay before the first retry, doubled on every retry
+    ///
+    /// @defaults to 100 milliseconds
+    pub initial_backoff: Duration,
+
+    /// Upper bound of the retry delay
+    ///
+    /// @defaults to 2 seconds
+    pub max_backoff: Duration,
+
+    /// Response statuses that are retried, besides connection failures and
+    /// timeouts
+    ///
+    /// @defaults to 502, 503, 504
+    pub retry_statuses: Vec<StatusCode>,
+
+    /// Retry requests whose method isn't idempotent, such as POST. Only
+    /// enable it when the called endpoints deduplicate requests
+    ///
+    /// @defaults to False
+    pub retry_non_idempotent: bool,
+
+    /// Headers copied from the incoming request by [NorthRequest::propagate]
+    ///
+    /// @defaults to [PROPAGATED_HEADERS]
+    pub propagated_headers: Vec<String>,
+
+    /// Refresh and outlier ejection of the instances of every called service
+    pub pool: ServicePoolOptions,
+}
+
+impl Default for NorthClientOptions {
+    fn default() -> Self {
+        NorthClientOptions {
+            timeout: Duration::from_secs(10),
+            retries: 2,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(2),
+            retry_statuses: vec![
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_non_idempotent: false,
+            propagated_headers: PROPAGATED_HEADERS.iter().map(|h| h.to_string()).collect(),
+            pool: ServicePoolOptions::default(),
+        }
+    }
+}
+
+impl NorthClientOptions {
+    /// Delay before the retry following `attempt`
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+/// Service name and path of a `http://<service>/<path>` URL
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ServiceUrl {
+    service: String,
+    path_and_query: String,
+}
+
+impl ServiceUrl {
+    fn parse(url: &str) -> Result<Self, Error> {
+        let url =
+            Url::parse(url).map_err(|e| Error::ClientError(format!("invalid url {url}: {e}")))?;
+        let service = url
+            .host_str()
+            .ok_or_else(|| Error::ClientError(format!("no service name in url {url}")))?;
+
+        let mut path_and_query = url.path().to_string();
+        if let Some(query) = url.query() {
+            path_and_query.push('?');
+            path_and_query.push_str(query);
+        }
+        Ok(ServiceUrl {
+            service: service.to_string(),
+            path_and_query,
+        })
+    }
+
+    /// URL of the request on one instance. The scheme of the instance wins
+    /// over the one of the service URL
+    fn on(&self, instance: &impl ServiceInstance) -> String {
+        format!("{}{}", instance.get_uri(), self.path_and_query)
+    }
+}
+
+/// ## NorthClient
+/// HTTP client for calls between services. The host of the URL is a service
+/// name whose instances are kept in a [ServicePool] refreshed from a
+/// [DiscoveryClient]. The instance is picked by a [BaseStrategy], round-robin
+/// unless set with [NorthClient::with_strategy].
+///
+/// Request stats of every instance are recorded in its
+/// [ServiceInstanceState], see [NorthClient::instance_state]
+///
+/// ```rust,ignore
+/// let client = NorthClient::new(discovery, NorthClientOptions::default());
+///
+/// let orders = client
+///     .get("http://orders-service/orders?status=open")
+///     .propagate(req.headers())
+///     .send()
+///     .await?
+///     .json::<Vec<Order>>()
+///     .await?;
+/// ```
+pub struct NorthClient<D, T> {
+    discovery: D,
+    http: reqwest::Client,
+    options: NorthClientOptions,
+    strategy: Arc<dyn BaseStrategy<T>>,
+    pools: Arc<Mutex<HashMap<String, ServicePool<T>>>>,
+}
+
+impl<D: Clone, T> Clone for NorthClient<D, T> {
+    fn clone(&self) -> Self {
+        NorthClient {
+            discovery: self.discovery.clone(),
+            http: self.http.clone(),
+            options: self.options.clone(),
+            strategy: self.strategy.clone(),
+            pools: self.pools.clone(),
+        }
+    }
+}
+
+impl<D, T> NorthClient<D, T>
+where
+    D: DiscoveryClient<T> + Clone + Send + Sync + 'static,
+    T: PooledServiceInstance + Clone + Send + Sync + 'static,
+{
+    pub fn new(discovery: D, options: NorthClientOptions) -> Self {
+        NorthClient {
+            discovery,
+            http: reqwest::Client::new(),
+            options,
+            strategy: Arc::new(RoundRobinStrategy::new()),
+            pools: Default::default(),
+        }
+    }
+
+    /// Load-balancing strategy picking the instance of every attempt
+    pub fn with_strategy(mut self, strategy: impl BaseStrategy<T> + 'static) -> Self {
+        self.strategy = Arc::new(strategy);
+        self
+    }
+
+    /// Uses a preconfigured reqwest client, e.g with TLS settings
+    pub fn with_http_client(mut self, http: reqwest::Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    pub fn request(&self, method: Method, url: &str) -> NorthRequest<'_, D, T> {
+        NorthRequest {
+            client: self,
+            method,
+            url: ServiceUrl::parse(url),
+            key: None,
+            headers: HeaderMap::new(),
+            body: None,
+            timeout: self.options.timeout,
+        }
+    }
+
+    pub fn get(&self, url: &str) -> NorthRequest<'_, D, T> {
+        self.request(Method::GET, url)
+    }
+
+    pub fn post(&self, url: &str) -> NorthRequest<'_, D, T> {
+        self.request(Method::POST, url)
+    }
+
+    pub fn put(&self, url: &str) -> NorthRequest<'_, D, T> {
+        self.request(Method::PUT, url)
+    }
+
+    pub fn patch(&self, url: &str) -> NorthRequest<'_, D, T> {
+        self.request(Method::PATCH, url)
+    }
+
+    pub fn delete(&self, url: &str) -> NorthRequest<'_, D, T> {
+        self.request(Method::DELETE, url)
+    }
+
+    /// Request stats recorded for an instance
+    pub fn instance_state(&self, instance_id: &str) -> Option<ServiceInstanceState> {
+        let pools = self.pools.lock().unwrap();
+        pools.values().find_map(|pool| pool.state(instance_id))
+    }
+
+    /// Snapshots of the pools of the called services, e.g for metrics
+    pub fn snapshots(&self) -> Vec<PoolSnapshot> {
+        let pools = self.pools.lock().unwrap();
+        pools.values().map(|pool| pool.snapshot()).collect()
+    }
+
+    /// Pool of a service, queried and set polling on the first call to it
+    async fn pool(&self, service: &str) -> Result<ServicePool<T>, Error> {
+        if let Some(pool) = self.pools.lock().unwrap().get(service) {
+            return Ok(pool.clone());
+        }
+
+        let pool = ServicePool::new(service, self.options.pool.clone());
+        pool.refresh(self.discovery.clone()).await?;
+        let mut pools = self.pools.lock().unwrap();
+        // a concurrent first call may have set the pool up meanwhile, only
+        // the inserted pool is polled
+        match pools.entry(service.to_string()) {
+            Entry::Vacant(entry) => {
+                entry.insert(pool.clone());
+                pool.spawn_polling(self.discovery.clone());
+                Ok(pool)
+            }
+            Entry::Occupied(entry) => Ok(entry.get().clone()),
+        }
+    }
+
+    /// Healthy instances of the service that aren't ejected
+    async fn instances(&self, service: &str) -> Result<Vec<T>, Error> {
+        let pool = self.pool(service).await?;
+        let instances = pool.instances();
+        if instances.is_empty() {
+            return Err(Error::ServiceUnavailable(format!(
+                "no available instance of {service}"
+            )));
+        }
+        Ok(instances)
+    }
+
+    /// Picks an instance, leaving out the ones that failed earlier attempts
+    /// unless no other is left
+    fn choose(&self, instances: &[T], failed: &[String], key: Option<&str>) -> Option<T> {
+        let remaining: Vec<T> = instances
+            .iter()
+            .filter(|instance| !failed.contains(&instance.get_instance_id()))
+            .cloned()
+            .collect();
+        let instances = match remaining.is_empty() {
+            true => instances,
+            false => &remaining,
+        };
+        self.strategy.choose(instances, key).cloned()
+    }
+
+    fn should_retry(&self, method: &Method, attempt: u32) -> bool {
+        let idempotent = matches!(
+            *method,
+            Method::GET | Method::HEAD | Method::OPTIONS | Method::PUT | Method::DELETE
+        );
+        attempt <= self.options.retries && (idempotent || self.options.retry_non_idempotent)
+    }
+
+    async fn execute(&self, request: NorthRequest<'_, D, T>) -> Result<Response, Error> {
+        let url = request.url?;
+        let mut attempt = 0;
+        let mut failed = vec![];
+
+        loop {
+            attempt += 1;
+            let instances = self.instances(&url.service).await?;
+            let instance = self
+                .choose(&instances, &failed, request.key.as_deref())
+                .ok_or_else(|| {
+                    Error::ServiceUnavailable(format!("no instance of {} picked", url.service))
+                })?;
+            let instance_id = instance.get_instance_id();
+
+            let mut builder = self
+                .http
+                .request(request.method.clone(), url.on(&instance))
+                .headers(request.headers.clone())
+                .timeout(request.timeout);
+            if let Some(body) = &request.body {
+                builder = builder.body(body.clone());
+            }
+
+            // the instance shares its state with the pool
+            let mut guard = instance.get_state().acquire();
+            let result = builder.send().await;
+
+            let error = match result {
+                Ok(response) => {
+                    let status = response.status();
+                    if status.is_server_error() {
+                        guard.fail();
+                    }
+                    drop(guard);
+                    if !self.options.retry_statuses.contains(&status)
+                        || !self.should_retry(&request.method, attempt)
+                    {
+                        return Ok(response);
+                    }
+                    format!("{instance_id} responded {status}")
+                }
+                Err(e) => {
+                    let message = format!("request to {instance_id} failed: {e}");
+                    guard.fail();
+                    drop(guard);
+                    if !self.should_retry(&request.method, attempt) {
+                        return Err(Error::ClientError(message));
+                    }
+                    message
+                }
+            };
+
+            failed.push(instance_id);
+            let delay = self.options.backoff(attempt);
+            log::warn!("{error}, retrying {} in {delay:?}", url.service);
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// A request built by a [NorthClient]
+pub struct NorthRequest<'a, D, T> {
+    client: &'a NorthClient<D, T>,
+    method: Method,
+    url: Result<ServiceUrl, Error>,
+    key: Option<String>,
+    headers: HeaderMap,
+    body: Option<Vec<u8>>,
+    timeout: Duration,
+}
+
+impl<'a, D, T> NorthRequest<'a, D, T>
+where
+    D: DiscoveryClient<T> + Clone + Send + Sync + 'static,
+    T: PooledServiceInstance + Clone + Send + Sync + 'static,
+{
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Copies the request ID and trace headers of the incoming request, so
+    /// the call shows up in the same trace
+    pub fn propagate(mut self, incoming: &HeaderMap) -> Self {
+        for name in self.client.options.propagated_headers.iter() {
+            if let Some(value) = incoming.get(name.as_str()) {
+                if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
+                    self.headers.insert(name, value.clone());
+                }
+            }
+        }
+        self
+    }
+
+    /// Routing key of the request, e.g a user ID, for strategies sending the
+    /// same key to the same instance
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    pub fn json<B: serde::Serialize>(mut self, body: &B) -> Self {
+        match serde_json::to_vec(body) {
+            Ok(body) => {
+                self.headers.insert(
+                    reqwest::header::CONTENT_TYPE,
+                    HeaderValue::from_static("application/json"),
+                );
+                self.body = Some(body);
+            }
+            Err(e) => self.url = Err(Error::ClientError(format!("invalid json body: {e}"))),
+        }
+        self
+    }
+
+    /// Overrides [NorthClientOptions::timeout] for this request
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends the request, retrying failed attempts on other instances
+    pub async fn send(self) -> Result<Response, Error> {
+        let client = self.client;
+        client.execute(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use north_common::registry::default_service_instance::DefaultServiceInstance;
+    use north_common::registry::service_instance::ServiceInstanceOptions;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[derive(Clone)]
+    struct StaticDiscovery {
+        instances: Vec<DefaultServiceInstance>,
+    }
+
+    #[async_trait]
+    impl DiscoveryClient<DefaultServiceInstance> for StaticDiscovery {
+        fn description(self) -> String {
+            "static".into()
+        }
+
+        async fn get_instances(self, _: String) -> Result<Vec<DefaultServiceInstance>, Error> {
+            Ok(self.instances)
+        }
+
+        async fn get_all_instances(self) -> Result<Vec<DefaultServiceInstance>, Error> {
+            Ok(self.instances)
+        }
+
+        async fn get_services(self) -> Result<Vec<String>, Error> {
+            Ok(vec!["orders-service".to_string()])
+        }
+    }
+
+    fn instance(id: &str, port: u16) -> DefaultServiceInstance {
+        DefaultServiceInstance::new(ServiceInstanceOptions {
+            instance_id: id.to_string(),
+            service_id: "orders-service".to_string(),
+            host: "127.0.0.1".to_string(),
+            port: port as u32,
+            status: "passing".to_string().into(),
+            ..Default::default()
+        })
+    }
+
+    /// Answers every connection with `status` and echoes the request head
+    async fn serve(status: &'static str) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0; 4096];
+                let read = stream.read(&mut buf).await.unwrap_or(0);
+                let head = String::from_utf8_lossy(&buf[..read]).to_lowercase();
+                let response = format!(
+                    "HTTP/1.1 {status}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{head}",
+                    head.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        port
+    }
+
+    fn options() -> NorthClientOptions {
+        NorthClientOptions {
+            initial_backoff: Duration::from_millis(1),
+            ..NorthClientOptions::default()
+        }
+    }
+
+    #[test]
+    fn it_parses_service_urls() {
+        let url = ServiceUrl::parse("http://orders-service/orders?status=open").unwrap();
+
+        assert_eq!(url.service, "orders-service");
+        assert_eq!(url.path_and_query, "/orders?status=open");
+        assert_eq!(
+            url.on(&instance("orders-1", 8080)),
+            "http://127.0.0.1:8080/orders?status=open"
+        );
+        assert!(ServiceUrl::parse("orders-service/orders").is_err());
+    }
+
+    #[tokio::test]
+    async fn it_propagates_headers_and_records_stats() {
+        let port = serve("200 OK").await;
+        let client = NorthClient::new(
+            StaticDiscovery {
+                instances: vec![instance("orders-1", port)],
+            },
+            options(),
+        );
+        let mut incoming = HeaderMap::new();
+        incoming.insert("x-request-id", HeaderValue::from_static("req-42"));
+        incoming.insert("authorization", HeaderValue::from_static("secret"));
+
+        let response = client
+            .get("http://orders-service/orders")
+            .propagate(&incoming)
+            .send()
+            .await
+            .unwrap();
+        let echoed = response.text().await.unwrap();
+
+        assert!(echoed.starts_with("get /orders http/1.1"));
+        assert!(echoed.contains("x-request-id: req-42"));
+        assert!(!echoed.contains("authorization"));
+        let state = client.instance_state("orders-1").unwrap();
+        assert_eq!(state.get_active_requests_count(None), 0);
+        assert_eq!(state.get_total_requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_retries_on_the_next_instance() {
+        let down = serve("503 Service Unavailable").await;
+        let up = serve("200 OK").await;
+        let client = NorthClient::new(
+            StaticDiscovery {
+                instances: vec![instance("orders-1", down), instance("orders-2", up)],
+            },
+            options(),
+        );
+
+        let response = client
+            .get("http://orders-service/orders")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // POST isn't retried by default
+        let response = client
+            .post("http://orders-service/orders")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
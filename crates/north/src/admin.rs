@@ -0,0 +1,311 @@
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeMap;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Instant;
+
+use north_common::utils::logger_utils::{log_levels, set_log_level};
+use poem::http::{header, StatusCode};
+use poem::web::{Data, Json, Path};
+use poem::{handler, post, Endpoint, EndpointExt, IntoResponse, Request, Response, Route};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::service::NorthServiceOptions;
+
+const REDACTED: &str = "******";
+
+/// Decides whether a request may use the admin endpoints
+#[async_trait::async_trait]
+pub trait AdminGuard: Send + Sync {
+    async fn authorize(&self, req: &Request) -> bool;
+}
+
+#[async_trait::async_trait]
+impl<F> AdminGuard for F
+where
+    F: Fn(&Request) -> bool + Send + Sync,
+{
+    async fn authorize(&self, req: &Request) -> bool {
+        self(req)
+    }
+}
+
+/// Only allows requests with an `Authorization: Bearer <token>` header
+pub struct BearerTokenGuard {
+    token: String,
+}
+
+impl BearerTokenGuard {
+    pub fn new(token: &str) -> Self {
+        BearerTokenGuard {
+            token: token.to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl AdminGuard for BearerTokenGuard {
+    async fn authorize(&self, req: &Request) -> bool {
+        req.headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| constant_time_eq(token.as_bytes(), self.token.as_bytes()))
+            .unwrap_or(false)
+    }
+}
+
+/// Compares tokens without returning early on the first differing byte, so
+/// response times don't leak how much of a guessed token is right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// ## AdminOptions
+/// Options of the `/_north` admin endpoints enabled with `with_admin`
+#[derive(Clone)]
+pub struct AdminOptions {
+    /// Guard checked before every admin request. Without a guard the admin
+    /// endpoints are open, so only leave it out on private networks
+    ///
+    /// @defaults to [None]
+    pub guard: Option<Arc<dyn AdminGuard>>,
+
+    /// Build metadata reported by `/info`, e.g git commit and build date
+    ///
+    /// @defaults to empty
+    pub build: BTreeMap<String, String>,
+
+    /// Application config reported by `/config`, secrets are redacted
+    ///
+    /// @defaults to [None]
+    pub app_config: Option<Value>,
+
+    /// Keys whose values are redacted from `/config`. Keys are matched
+    /// case-insensitively and partially, so `password` also hides
+    /// `db_password`
+    ///
+    /// @defaults to password, secret, token, key, credential
+    pub redacted_keys: Vec<String>,
+
+    /// ID reported by `/info`. A random ID is generated when missing
+    ///
+    /// @defaults to [None]
+    pub instance_id: Option<String>,
+}
+
+impl Default for AdminOptions {
+    fn default() -> Self {
+        AdminOptions {
+            guard: None,
+            build: BTreeMap::new(),
+            app_config: None,
+            redacted_keys: ["password", "secret", "token", "key", "credential"]
+                .iter()
+                .map(|key| key.to_string())
+                .collect(),
+            instance_id: None,
+        }
+    }
+}
+
+impl AdminOptions {
+    pub fn guard(mut self, guard: impl AdminGuard + 'static) -> Self {
+        self.guard = Some(Arc::new(guard));
+        self
+    }
+
+    pub fn build_info(mut self, name: &str, value: &str) -> Self {
+        self.build.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn app_config<C: Serialize>(mut self, config: &C) -> Self {
+        self.app_config = serde_json::to_value(config).ok();
+        self
+    }
+}
+
+#[derive(Clone)]
+struct AdminState {
+    info: Value,
+    config: Value,
+    started: Instant,
+}
+
+/// Mounts `/info`, `/config`, `/loggers` and `/routes` behind the guard
+pub(crate) fn admin_routes(
+    options: &AdminOptions,
+    service_options: &NorthServiceOptions,
+    routes: Route,
+) -> Route {
+    let instance_id = options.instance_id.clone().unwrap_or_else(|| {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        format!("{:016x}", hasher.finish())
+    });
+
+    let mut config = json!({
+        "service": service_config(service_options),
+        "app": options.app_config.clone().unwrap_or(Value::Null),
+    });
+    redact(&mut config, &options.redacted_keys);
+
+    let state = AdminState {
+        info: json!({
+            "name": service_options.name,
+            "version": service_options.version,
+            "instance_id": instance_id,
+            "build": options.build,
+        }),
+        config,
+        started: Instant::now(),
+    };
+
+    let admin = Route::new()
+        .at("/info", show_info)
+        .at("/config", show_config)
+        .at("/loggers", list_loggers)
+        .at("/loggers/:name", post(update_logger))
+        .nest("/routes", routes)
+        .data(state);
+
+    let guard = options.guard.clone();
+    Route::new().nest(
+        "/",
+        admin.around(move |ep, req| {
+            let guard = guard.clone();
+            async move {
+                if let Some(guard) = guard {
+                    if !guard.authorize(&req).await {
+                        return Ok(StatusCode::UNAUTHORIZED.into_response());
+                    }
+                }
+                ep.call(req).await.map(IntoResponse::into_response)
+            }
+        }),
+    )
+}
+
+fn service_config(options: &NorthServiceOptions) -> Value {
+    json!({
+        "address": options.address,
+        "name": options.name,
+        "path_prefix": options.path_prefix,
+        "version": options.version,
+        "port": options.port,
+        "graceful_shutdown": options.graceful_shutdown,
+        "verbose": options.verbose,
+        "enable_swagger": options.enable_swagger,
+        "auto_acme": options.auto_acme,
+        "keep_alive": options.keep_alive,
+        "read_timeout": options.read_timeout,
+        "write_timeout": options.write_timeout,
+        "shutdown_timeout": options.shutdown_timeout,
+        "registry": options.registry.is_some(),
+    })
+}
+
+/// Replaces the values of secret looking keys, at any depth
+fn redact(value: &mut Value, keys: &[String]) {
+    match value {
+        Value::Object(map) => {
+            for (name, value) in map.iter_mut() {
+                let name = name.to_lowercase();
+                if keys.iter().any(|key| name.contains(&key.to_lowercase())) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value, keys);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact(item, keys)),
+        _ => {}
+    }
+}
+
+#[handler]
+fn show_info(state: Data<&AdminState>) -> Json<Value> {
+    let mut info = state.info.clone();
+    info["uptime_seconds"] = json!(state.started.elapsed().as_secs());
+    Json(info)
+}
+
+#[handler]
+fn show_config(state: Data<&AdminState>) -> Json<Value> {
+    Json(state.config.clone())
+}
+
+#[handler]
+fn list_loggers() -> Json<BTreeMap<String, String>> {
+    Json(
+        log_levels()
+            .into_iter()
+            .map(|(name, level)| (name, level.to_string().to_lowercase()))
+            .collect(),
+    )
+}
+
+#[derive(Deserialize)]
+struct LoggerLevel {
+    level: String,
+}
+
+#[handler]
+fn update_logger(Path(name): Path<String>, Json(body): Json<LoggerLevel>) -> Response {
+    match set_log_level(&name, &body.level) {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_redacts_nested_secrets() {
+        let mut config = json!({
+            "database": { "url": "postgres://localhost", "db_password": "hunter2" },
+            "clients": [{ "api_key": "abc" }],
+        });
+        redact(&mut config, &AdminOptions::default().redacted_keys);
+
+        assert_eq!(
+            config,
+            json!({
+                "database": { "url": "postgres://localhost", "db_password": REDACTED },
+                "clients": [{ "api_key": REDACTED }],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn it_rejects_requests_refused_by_the_guard() {
+        let options = AdminOptions::default().guard(BearerTokenGuard::new("s3cret"));
+        let app = admin_routes(&options, &NorthServiceOptions::default(), Route::new());
+        let client = poem::test::TestClient::new(app);
+
+        client
+            .get("/info")
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+        client
+            .get("/info")
+            .header(header::AUTHORIZATION, "Bearer s3cre")
+            .send()
+            .await
+            .assert_status(StatusCode::UNAUTHORIZED);
+        client
+            .get("/info")
+            .header(header::AUTHORIZATION, "Bearer s3cret")
+            .send()
+            .await
+            .assert_status_is_ok();
+    }
+}
@@ -30,6 +30,10 @@ pub trait NorthServiceBuilderTrait<T> {
     /// takes in the name of the service
     fn write_timeout(self, timeout: u32) -> Self;
 
+    /// seconds given to in-flight requests and websocket sessions when
+    /// shutting down gracefully
+    fn shutdown_timeout(self, timeout: u32) -> Self;
+
     /// takes in the name of the service
     fn address(self, address: &str) -> Self;
 
@@ -38,29 +42,146 @@ pub trait NorthServiceBuilderTrait<T> {
 
     fn wrapper(self) -> Self;
 
+    /// serves the app in place of the controller, handlers, GraphQL schemas
+    /// and docs. Websockets, static sites, gRPC services, the health routes and
+    /// the admin endpoints are still mounted
     #[cfg(feature = "api-poem")]
     fn custom_http_server(self, app: Route) -> Self;
 
     #[cfg(feature = "api-poem")]
     fn controller(self, api: T) -> Self;
 
+    /// mounts an async-graphql schema under the path prefix. Queries and
+    /// mutations are served over POST and GET, subscriptions over websocket,
+    /// and state data is available from the resolver context
+    #[cfg(feature = "graphql")]
+    fn graphql<E: GraphQLExecutor>(self, path: &str, schema: E) -> Self;
+
+    /// serves GraphiQL on GET requests to the GraphQL paths
+    #[cfg(feature = "graphql")]
+    fn with_graphiql(self, enable_graphiql: bool) -> Self;
+
+    /// mounts a websocket handler under the path prefix
+    #[cfg(feature = "api-poem")]
+    fn websocket<F, Fut>(self, path: &str, handler: F) -> Self
+    where
+        F: Fn(WebSocketSession) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static;
+
+    /// keepalive, message size and buffering options of websocket routes
+    #[cfg(feature = "api-poem")]
+    fn websocket_options(self, options: WebSocketOptions) -> Self;
+
+    /// serves the files of a directory at `mount`. Routes under the path
+    /// prefix are always matched first
+    #[cfg(feature = "api-poem")]
+    fn static_files(self, mount: &str, dir: impl Into<PathBuf>, options: StaticOptions) -> Self;
+
+    /// serves files embedded with rust-embed at `mount`. Routes under the
+    /// path prefix are always matched first
+    #[cfg(feature = "api-poem")]
+    fn embedded_files<E: RustEmbed>(self, mount: &str, options: StaticOptions) -> Self;
+
+    /// hosts a tonic service. gRPC health is served for every service and
+    /// reflection when descriptor sets are configured
+    #[cfg(feature = "grpc")]
+    fn grpc_service<S: TonicService>(self, service: S) -> Self;
+
+    /// port and reflection options of the gRPC services
+    #[cfg(feature = "grpc")]
+    fn grpc_options(self, options: GrpcOptions) -> Self;
+
     fn with_swagger(self, enable_swagger: bool) -> Self;
 
+    /// Enable sessions. Handlers read them with `TypedSession<T>` or poem's
+    /// `&Session`
+    #[cfg(feature = "api-poem")]
+    fn with_sessions(self, config: SessionConfig) -> Self;
+
+    /// Log every request to the `requests` logger, in Common, Combined or
+    /// JSON format
+    #[cfg(feature = "api-poem")]
+    fn with_access_log(self, options: AccessLogOptions) -> Self;
+
+    /// Replay the first response of requests repeating an `Idempotency-Key`
+    /// header, so clients can safely retry POST requests
+    #[cfg(feature = "api-poem")]
+    fn with_idempotency(self, options: IdempotencyOptions) -> Self;
+
     /// Add a database connection to the state
     #[cfg(feature = "db-arango")]
     fn with_database(self, db_connection: Arc<DatabaseConnection>) -> Self;
 
+    /// Run SQL migrations before the listener binds. Readiness reports `DOWN`
+    /// until the migrations complete.
+    #[cfg(feature = "db-sql")]
+    fn with_migrations(self, options: MigrationOptions) -> Self;
+
+    /// Mount the `/_north` admin endpoints: info, effective config, runtime
+    /// log levels and the route table
+    #[cfg(feature = "api-poem")]
+    fn with_admin(self, options: AdminOptions) -> Self;
+
+    /// Replace the default logging setup: console or JSON output, log files
+    /// with rotation and per-module levels
+    fn with_logger(self, options: LoggerOptions) -> Self;
+
     /// Enable auto SSL with lets encrypt acme
     fn with_auto_acme(self, enable_acme: bool) -> Self;
 
     fn service_registry(self, registry: BoxedServiceRegistry) -> Self;
 
-    /// Used to pass state or context through to the handlers
+    /// Used to pass state or context through to the handlers. Handlers
+    /// extract it as `Data<&Arc<S>>`, GraphQL resolvers as `Arc<S>`
     #[cfg(feature = "api-poem")]
     fn with_data<S: NorthStateData + Send + Sync + 'static>(self, data: S) -> Self;
 
+    /// Registers an SSE channel as state data. Unlike [Self::with_data],
+    /// handlers extract the channel itself as `Data<&SseChannel<E>>`, GraphQL
+    /// resolvers as `SseChannel<E>`
+    #[cfg(feature = "api-poem")]
+    fn sse_channel<E: Clone + Send + Sync + 'static>(self, channel: SseChannel<E>) -> Self;
+
+    /// Run a hook before the listener binds, e.g to warm caches. A failing
+    /// hook aborts startup
+    fn on_start(self, hook: impl Into<LifecycleHook>) -> Self;
+
+    /// Run a hook once the listener accepts connections, e.g to announce
+    /// the service
+    fn on_ready(self, hook: impl Into<LifecycleHook>) -> Self;
+
+    /// Run a hook when the shutdown signal is received, before in-flight
+    /// requests drain, e.g to stop consumers
+    fn on_shutdown(self, hook: impl Into<LifecycleHook>) -> Self;
+
+    /// Run a hook after the server stopped, e.g to flush telemetry
+    fn on_stopped(self, hook: impl Into<LifecycleHook>) -> Self;
+
+    /// Run a long lived task next to the server, e.g a queue consumer. The
+    /// task is restarted with backoff when it fails or panics. It is reported
+    /// as the `worker:<name>` readiness component when
+    /// [WorkerOptions::report_readiness] is set
+    fn worker<F, Fut>(self, name: &str, factory: F) -> Self
+    where
+        F: Fn(WorkerContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static;
+
+    /// Run a job on a cron schedule, e.g `*/5 * * * *`. The outcome of the
+    /// last run is reported as the `job:<name>` readiness component when
+    /// [WorkerOptions::report_readiness] is set
+    fn schedule<F, Fut>(self, name: &str, cron: &str, job: F) -> Self
+    where
+        F: Fn(WorkerContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static;
+
+    /// Restart backoff and shutdown grace period of workers and jobs
+    fn worker_options(self, options: WorkerOptions) -> Self;
+
     /// Gracefully shutdown when the SIGTERM is called
     fn graceful_shutdown(self) -> Self;
 
+    /// Print the configuration and the route table on startup
+    fn verbose(self) -> Self;
+
     fn build(&mut self) -> NorthService;
 }
@@ -95,6 +95,30 @@ impl ResponseError for Error {
     }
 }
 
+impl std::error::Error for Error {}
+
+/// Convert NorthErrors to poem errors with a matching status code
+#[cfg(feature = "api-poem")]
+impl poem::error::ResponseError for Error {
+    fn status(&self) -> poem::http::StatusCode {
+        use poem::http::StatusCode;
+
+        match self {
+            Error::BadRequest(_error) => StatusCode::BAD_REQUEST,
+            Error::NotFound(_message) => StatusCode::NOT_FOUND,
+            Error::ValidationError(_errors) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::Unauthorized(_error) => StatusCode::UNAUTHORIZED,
+            Error::Conflict(_message) => StatusCode::CONFLICT,
+            Error::RequestTimeout(_message) => StatusCode::REQUEST_TIMEOUT,
+            Error::Gone(_errors) => StatusCode::GONE,
+            Error::PaymentRequired(_error) => StatusCode::PAYMENT_REQUIRED,
+            Error::PayloadTooLarge(_error) => StatusCode::PAYLOAD_TOO_LARGE,
+            Error::TooManyRequests(_error) => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
 /// Utility to make transforming a string reference into an ErrorResponse
 impl From<&String> for ErrorResponse {
     fn from(error: &String) -> Self {
@@ -144,3 +168,27 @@ impl From<AddrParseError> for Error {
         Error::InternalServerError(error.to_string())
     }
 }
+
+/// Convert RedisError to NorthErrors
+#[cfg(feature = "session-redis")]
+impl From<redis::RedisError> for Error {
+    fn from(error: redis::RedisError) -> Error {
+        Error::CacheError(error.to_string())
+    }
+}
+
+/// Convert sqlx::Error to NorthErrors
+#[cfg(feature = "db-sql")]
+impl From<sqlx::Error> for Error {
+    fn from(error: sqlx::Error) -> Error {
+        Error::DatabaseError(error.to_string())
+    }
+}
+
+/// Convert MigrateError to NorthErrors
+#[cfg(feature = "db-sql")]
+impl From<sqlx::migrate::MigrateError> for Error {
+    fn from(error: sqlx::migrate::MigrateError) -> Error {
+        Error::DatabaseError(error.to_string())
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+#[cfg(feature = "api-poem")]
+use poem::{endpoint::make_sync, http::StatusCode, Response, Route};
+
+/// Status reported by a health component and by the aggregated report
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+/// Health of a single named component
+#[derive(Clone, Debug, Serialize)]
+pub struct ComponentHealth {
+    pub status: HealthStatus,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+/// Aggregated health report served by the readiness endpoint
+#[derive(Clone, Debug, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub components: BTreeMap<String, ComponentHealth>,
+}
+
+/// ## HealthIndicators
+/// Shared registry of readiness components. The service is only reported as
+/// ready when every registered component is `UP`.
+#[derive(Clone, Default)]
+pub struct HealthIndicators {
+    components: Arc<RwLock<BTreeMap<String, ComponentHealth>>>,
+}
+
+impl HealthIndicators {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// marks a component as healthy
+    pub fn up(&self, name: &str) {
+        self.set(name, HealthStatus::Up, None);
+    }
+
+    /// marks a component as unhealthy with a reason
+    pub fn down(&self, name: &str, details: impl Into<String>) {
+        self.set(name, HealthStatus::Down, Some(details.into()));
+    }
+
+    /// sets the status of a component, registering it if needed
+    pub fn set(&self, name: &str, status: HealthStatus, details: Option<String>) {
+        let mut components = self.components.write().unwrap();
+        components.insert(name.to_string(), ComponentHealth { status, details });
+    }
+
+    /// removes a component from the readiness report
+    pub fn remove(&self, name: &str) {
+        self.components.write().unwrap().remove(name);
+    }
+
+    /// aggregated status of all components
+    pub fn status(&self) -> HealthStatus {
+        let components = self.components.read().unwrap();
+        if components.values().all(|c| c.status == HealthStatus::Up) {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        }
+    }
+
+    /// snapshot of all components and the aggregated status
+    pub fn report(&self) -> HealthReport {
+        HealthReport {
+            status: self.status(),
+            components: self.components.read().unwrap().clone(),
+        }
+    }
+}
+
+/// Builds the `/health` routes. `/liveness` only tells that the process is
+/// serving requests while `/readiness` aggregates every registered component.
+#[cfg(feature = "api-poem")]
+pub(crate) fn health_routes(health: HealthIndicators) -> Route {
+    let readiness = health.clone();
+
+    Route::new()
+        .at("/", make_sync(move |_| report_response(&health.report())))
+        .at(
+            "/readiness",
+            make_sync(move |_| report_response(&readiness.report())),
+        )
+        .at(
+            "/liveness",
+            make_sync(|_| {
+                report_response(&HealthReport {
+                    status: HealthStatus::Up,
+                    components: BTreeMap::new(),
+                })
+            }),
+        )
+}
+
+#[cfg(feature = "api-poem")]
+fn report_response(report: &HealthReport) -> Response {
+    let status = match report.status {
+        HealthStatus::Up => StatusCode::OK,
+        HealthStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+    };
+
+    Response::builder()
+        .status(status)
+        .content_type("application/json")
+        .body(serde_json::to_string(report).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reports_up_without_components() {
+        let health = HealthIndicators::new();
+        assert_eq!(health.status(), HealthStatus::Up);
+    }
+
+    #[test]
+    fn it_reports_down_when_any_component_is_down() {
+        let health = HealthIndicators::new();
+        health.up("database");
+        health.down("migrations", "2 pending");
+        assert_eq!(health.status(), HealthStatus::Down);
+
+        health.up("migrations");
+        assert_eq!(health.status(), HealthStatus::Up);
+    }
+}
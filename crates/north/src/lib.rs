@@ -7,27 +7,56 @@ extern crate tuple;
 
 mod macros;
 
+#[cfg(feature = "api-poem")]
+pub mod admin;
 pub mod contracts;
 mod error;
 mod north;
 mod prelude;
 mod router;
+pub mod routes;
 #[cfg(feature = "api-native")]
 mod server;
 mod service;
 pub mod web;
 
 mod addr;
+#[cfg(feature = "db-sql")]
+pub mod db;
+pub mod health;
 pub mod helper;
+mod lifecycle;
+mod shutdown;
 mod utils;
+mod workers;
 
+pub use self::routes::{print_routes, RouteInfo, RouteSource};
 pub use self::utils::server_utils::print_server_info;
 pub use self::utils::server_utils::NorthResult;
 
+#[cfg(feature = "grpc")]
+pub use self::web::grpc::{GrpcOptions, TonicService};
+#[cfg(feature = "api-poem")]
+pub use self::web::{
+    access_log::{AccessLogFormat, AccessLogOptions},
+    assets::StaticOptions,
+    idempotency::{
+        IdempotencyClaim, IdempotencyOptions, IdempotencyStore, MemoryIdempotencyStore,
+        StoredResponse,
+    },
+    session::{CookieSecurity, SessionConfig, SessionStore, SessionValue, TypedSession},
+    sse::{SseChannel, SseOptions, SseStream},
+    websocket::{BroadcastHub, WebSocketOptions, WebSocketSession},
+};
 pub use {
     self::contracts::NorthServiceBuilderTrait,
     self::error::{Error, ErrorResponse},
+    self::health::{HealthIndicators, HealthStatus},
+    self::lifecycle::{LifecycleHook, LifecycleStage},
     self::north::{new_service, power, North},
     self::service::NorthServiceOptions,
-    north_common::state::NorthStateData,
+    self::shutdown::ShutdownHandle,
+    self::workers::{WorkerContext, WorkerOptions},
+    north_common::state::{NorthStateData, NorthStateMap},
+    north_common::utils::logger_utils::{LogFileOptions, LogFormat, LogRotation, LoggerOptions},
 };
@@ -0,0 +1,224 @@
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use north_common::state::NorthStateMap;
+
+use crate::error::Error;
+
+/// Time a hook may run before it is abandoned
+const DEFAULT_HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Point of the service lifecycle a hook runs at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LifecycleStage {
+    /// Before the listener binds. A failing hook aborts startup
+    Start,
+
+    /// Once the listener accepts connections
+    Ready,
+
+    /// When the shutdown signal is received, before in-flight requests drain
+    Shutdown,
+
+    /// After the server stopped and requests drained
+    Stopped,
+}
+
+impl fmt::Display for LifecycleStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let stage = match self {
+            LifecycleStage::Start => "start",
+            LifecycleStage::Ready => "ready",
+            LifecycleStage::Shutdown => "shutdown",
+            LifecycleStage::Stopped => "stopped",
+        };
+        f.write_str(stage)
+    }
+}
+
+type HookFn = Arc<dyn Fn(NorthStateMap) -> BoxFuture<'static, Result<(), Error>> + Send + Sync>;
+
+/// ## LifecycleHook
+/// Async code run at a lifecycle stage, with access to the service state
+///
+/// ```rust,ignore
+/// new_service::<Api>()
+///     .with_data(cache.clone())
+///     .on_start(
+///         LifecycleHook::new(|state| async move {
+///             state.get::<Cache>().unwrap().warm().await
+///         })
+///         .name("warm cache")
+///         .timeout(Duration::from_secs(60)),
+///     )
+/// ```
+#[derive(Clone)]
+pub struct LifecycleHook {
+    name: String,
+    timeout: Duration,
+    run: HookFn,
+}
+
+impl LifecycleHook {
+    pub fn new<F, Fut>(hook: F) -> Self
+    where
+        F: Fn(NorthStateMap) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        LifecycleHook {
+            name: "hook".to_string(),
+            timeout: DEFAULT_HOOK_TIMEOUT,
+            run: Arc::new(move |state| hook(state).boxed()),
+        }
+    }
+
+    /// Name used in logs and errors
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// @defaults to 30 seconds
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    async fn call(&self, stage: LifecycleStage, state: NorthStateMap) -> Result<(), Error> {
+        match tokio::time::timeout(self.timeout, (self.run)(state)).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(Error::InternalServerError(format!(
+                "{stage} hook `{}` failed: {e}",
+                self.name
+            ))),
+            Err(_) => Err(Error::RequestTimeout(format!(
+                "{stage} hook `{}` timed out after {:?}",
+                self.name, self.timeout
+            ))),
+        }
+    }
+}
+
+impl<F, Fut> From<F> for LifecycleHook
+where
+    F: Fn(NorthStateMap) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), Error>> + Send + 'static,
+{
+    fn from(hook: F) -> Self {
+        LifecycleHook::new(hook)
+    }
+}
+
+/// Hooks registered on a service, run in registration order
+#[derive(Clone, Default)]
+pub(crate) struct LifecycleHooks {
+    start: Vec<LifecycleHook>,
+    ready: Vec<LifecycleHook>,
+    shutdown: Vec<LifecycleHook>,
+    stopped: Vec<LifecycleHook>,
+}
+
+impl LifecycleHooks {
+    pub(crate) fn add(&mut self, stage: LifecycleStage, hook: LifecycleHook) {
+        self.stage_mut(stage).push(hook);
+    }
+
+    fn stage_mut(&mut self, stage: LifecycleStage) -> &mut Vec<LifecycleHook> {
+        match stage {
+            LifecycleStage::Start => &mut self.start,
+            LifecycleStage::Ready => &mut self.ready,
+            LifecycleStage::Shutdown => &mut self.shutdown,
+            LifecycleStage::Stopped => &mut self.stopped,
+        }
+    }
+
+    fn stage(&self, stage: LifecycleStage) -> &[LifecycleHook] {
+        match stage {
+            LifecycleStage::Start => &self.start,
+            LifecycleStage::Ready => &self.ready,
+            LifecycleStage::Shutdown => &self.shutdown,
+            LifecycleStage::Stopped => &self.stopped,
+        }
+    }
+
+    /// Runs the hooks of a stage. Start hooks stop at the first failure and
+    /// return it, failures of the other stages are logged so every hook runs
+    pub(crate) async fn run(
+        &self,
+        stage: LifecycleStage,
+        state: &NorthStateMap,
+    ) -> Result<(), Error> {
+        for hook in self.stage(stage) {
+            match hook.call(stage, state.clone()).await {
+                Ok(()) => {}
+                Err(e) if stage == LifecycleStage::Start => return Err(e),
+                Err(e) => log::error!("{e}"),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn counting(calls: Arc<AtomicUsize>) -> LifecycleHook {
+        LifecycleHook::new(move |_| {
+            let calls = calls.clone();
+            async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        })
+    }
+
+    fn failing() -> LifecycleHook {
+        LifecycleHook::new(|_| async { Err(Error::InternalServerError("boom".to_string())) })
+            .name("failing")
+    }
+
+    #[tokio::test]
+    async fn it_aborts_on_the_first_failing_start_hook() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut hooks = LifecycleHooks::default();
+        hooks.add(LifecycleStage::Start, failing());
+        hooks.add(LifecycleStage::Start, counting(calls.clone()));
+
+        let result = hooks
+            .run(LifecycleStage::Start, &NorthStateMap::new())
+            .await;
+
+        assert_eq!(
+            result,
+            Err(Error::InternalServerError(
+                "start hook `failing` failed: boom".to_string()
+            ))
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn it_keeps_running_shutdown_hooks_after_failures() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut hooks = LifecycleHooks::default();
+        hooks.add(LifecycleStage::Shutdown, failing());
+        hooks.add(
+            LifecycleStage::Shutdown,
+            LifecycleHook::new(|_| futures::future::pending()).timeout(Duration::from_millis(10)),
+        );
+        hooks.add(LifecycleStage::Shutdown, counting(calls.clone()));
+
+        let result = hooks
+            .run(LifecycleStage::Shutdown, &NorthStateMap::new())
+            .await;
+
+        assert!(result.is_ok());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
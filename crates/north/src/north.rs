@@ -1,11 +1,25 @@
+use crate::lifecycle::{LifecycleHooks, LifecycleStage};
+use crate::routes::print_routes;
 use crate::service::{NorthService, NorthServiceBuilder};
+use crate::utils::server_utils::{print_server_info, shutdown_signal};
+#[cfg(feature = "grpc")]
+use crate::web::grpc::{grpc_routes, GrpcDispatch};
+#[cfg(feature = "api-poem")]
+use crate::web::{
+    access_log::AccessLog, assets::StaticFallback, idempotency::Idempotency,
+    session::session_endpoint,
+};
+use north_common::state::NorthStateMap;
 use north_common::utils::logger_utils::init_logger;
+#[cfg(feature = "grpc")]
+use poem::endpoint::BoxEndpoint;
 #[cfg(feature = "api-poem")]
 use poem::{
-    listener::TcpListener,
+    listener::{Acceptor, Listener, TcpListener},
     middleware::{AddData, TokioMetrics, Tracing},
     EndpointExt,
 };
+use std::time::Duration;
 
 use north_derives::process_poem;
 
@@ -78,23 +92,174 @@ impl North {
     }
 
     // #[cfg(all(feature = "api-poem", not(feature = "api-native")))]
-    pub async fn up(self) -> std::io::Result<()> {
+    pub async fn up(mut self) -> std::io::Result<()> {
+        #[cfg(feature = "db-sql")]
+        if let Some(migrations) = self.service.migrations.take() {
+            crate::db::migrations::run_migrations(migrations, &self.service.health)
+                .await
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        }
+
+        let hooks = self.service.hooks.clone();
+        let state_map = self.service.state.clone();
+        hooks
+            .run(LifecycleStage::Start, &state_map)
+            .await
+            .map_err(|e| {
+                std::io::Error::new(std::io::ErrorKind::Other, format!("startup aborted: {e}"))
+            })?;
+
         let full_addr = format!(
             "{}:{}",
             self.service.options.address.clone().unwrap(),
             self.service.options.port.unwrap(),
         );
+        let acceptor = TcpListener::bind(full_addr).into_acceptor().await?;
+
+        let workers = self
+            .service
+            .workers
+            .start(&state_map, &self.service.health, &self.service.shutdown)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+
+        // from here on workers run, so every outcome goes through the same
+        // cleanup
+        let result = self.serve(acceptor, &hooks, &state_map).await;
+
+        self.service.shutdown.trigger();
+        workers.stop().await;
+        if let Err(e) = hooks.run(LifecycleStage::Stopped, &state_map).await {
+            log::error!("stopped hooks failed: {e}");
+        }
+        result
+    }
 
+    /// Builds the app and serves it on the acceptor until the shutdown
+    /// signal. Shutdown hooks run on the signal whether or not the shutdown
+    /// is graceful; without `graceful_shutdown` in-flight requests are
+    /// dropped instead of drained
+    async fn serve(
+        &mut self,
+        acceptor: impl Acceptor + 'static,
+        hooks: &LifecycleHooks,
+        state_map: &NorthStateMap,
+    ) -> std::io::Result<()> {
         let main_metrics = TokioMetrics::new();
-        let app = self.service.poem_app;
-        let end = app
+        let app = *std::mem::take(&mut self.service.poem_app);
+        let mut end = app
             .at("/metrics/default", main_metrics.exporter())
-            .with(Tracing);
-        let state = self.service.state_data_list.as_slice();
-        let d = [..state];
+            .with(Tracing)
+            .with(AddData::new(self.service.state.clone()))
+            .with(AddData::new(self.service.hub.clone()))
+            .map_to_response()
+            .boxed();
+        if !self.service.static_sites.is_empty() {
+            end = StaticFallback::new(
+                end,
+                self.service.static_sites.clone(),
+                self.service.options.path_prefix.as_deref(),
+            )
+            .boxed();
+        }
+        if let Some(sessions) = self.service.sessions.as_ref() {
+            end = session_endpoint(end, sessions)
+                .await
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+        }
+        for inject in self.service.data_injectors.iter() {
+            end = inject(end);
+        }
+        if let Some(idempotency) = self.service.idempotency.clone() {
+            end = Idempotency::new(end, idempotency).boxed();
+        }
+        #[cfg(feature = "grpc")]
+        if !self.service.grpc_services.is_empty() {
+            end = self.serve_grpc(end).await?;
+        }
+        if let Some(access_log) = self.service.access_log.clone() {
+            end = AccessLog::new(end, access_log).boxed();
+        }
 
-        poem::Server::new(TcpListener::bind(full_addr))
-            .run(process_poem!(end, ["2", "5"]))
-            .await
+        if self.service.options.verbose {
+            print_server_info(&self.service.options);
+            print_routes(self.service.routes());
+        }
+
+        // ready hooks run next to the server so they can call the service
+        let ready = {
+            let (hooks, state_map) = (hooks.clone(), state_map.clone());
+            tokio::spawn(async move {
+                if let Err(e) = hooks.run(LifecycleStage::Ready, &state_map).await {
+                    log::error!("ready hooks failed: {e}");
+                }
+            })
+        };
+
+        let timeout = match self.service.options.graceful_shutdown {
+            true => self.shutdown_timeout(),
+            false => Duration::ZERO,
+        };
+        let shutdown = self.service.shutdown.clone();
+        let (hooks, state_map) = (hooks.clone(), state_map.clone());
+        let result = poem::Server::new_with_acceptor(acceptor)
+            .run_with_graceful_shutdown(
+                process_poem!(end, ["2", "5"]),
+                async move {
+                    shutdown_signal().await;
+                    shutdown.trigger();
+                    if let Err(e) = hooks.run(LifecycleStage::Shutdown, &state_map).await {
+                        log::error!("shutdown hooks failed: {e}");
+                    }
+                },
+                Some(timeout),
+            )
+            .await;
+        ready.abort();
+        result
+    }
+
+    fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.service.options.shutdown_timeout as u64)
+    }
+
+    /// Mounts the gRPC services on the HTTP app, or starts them on their own
+    /// port. Both share the shutdown signal and tracing of the HTTP server
+    #[cfg(feature = "grpc")]
+    async fn serve_grpc(&self, end: BoxEndpoint<'static>) -> std::io::Result<BoxEndpoint<'static>> {
+        let (route, mut health) =
+            grpc_routes(&self.service.grpc_services, &self.service.options.grpc)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+
+        health.set_serving(true).await;
+        let shutdown = self.service.shutdown.clone();
+        tokio::spawn(async move {
+            shutdown.wait().await;
+            health.set_serving(false).await;
+        });
+
+        let port = match self.service.options.grpc.port {
+            Some(port) => port,
+            None => {
+                let grpc = route.with(Tracing).map_to_response().boxed();
+                return Ok(GrpcDispatch { http: end, grpc }.boxed());
+            }
+        };
+
+        let grpc_addr = format!("{}:{}", self.service.options.address.clone().unwrap(), port);
+        let shutdown = self.service.shutdown.clone();
+        let timeout = self.shutdown_timeout();
+        tokio::spawn(async move {
+            let result = poem::Server::new(TcpListener::bind(grpc_addr))
+                .run_with_graceful_shutdown(
+                    route.with(Tracing),
+                    async move { shutdown.wait().await },
+                    Some(timeout),
+                )
+                .await;
+            if let Err(e) = result {
+                log::error!("gRPC server stopped: {e}");
+            }
+        });
+        Ok(end)
     }
 }
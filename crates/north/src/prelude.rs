@@ -1,18 +1,67 @@
+#[cfg(feature = "api-poem")]
+pub(crate) use crate::admin::admin_routes;
+#[cfg(feature = "api-poem")]
+pub use crate::admin::AdminOptions;
 pub use crate::contracts::*;
+#[cfg(feature = "db-sql")]
+pub use crate::db::migrations::MigrationOptions;
+pub(crate) use crate::error::Error;
+#[cfg(feature = "api-poem")]
+pub(crate) use crate::health::health_routes;
+pub use crate::health::HealthIndicators;
+pub(crate) use crate::lifecycle::LifecycleHooks;
+pub use crate::lifecycle::{LifecycleHook, LifecycleStage};
+#[cfg(feature = "api-poem")]
+pub(crate) use crate::routes::{controller_routes, routes_endpoint};
+pub use crate::routes::{RouteInfo, RouteSource};
 pub use crate::service::*;
+pub use crate::shutdown::ShutdownHandle;
 #[cfg(feature = "db-arango")]
 pub use crate::utils::boxed_connection::ArcArangoConnection;
+#[cfg(feature = "api-poem")]
+pub use crate::web::access_log::AccessLogOptions;
+#[cfg(feature = "api-poem")]
+pub use crate::web::assets::StaticOptions;
+#[cfg(feature = "api-poem")]
+pub(crate) use crate::web::assets::StaticSite;
+#[cfg(feature = "graphql")]
+pub(crate) use crate::web::graphql::{self, GraphQLDataInjector, GraphQLFactory, GraphQLMount};
+#[cfg(feature = "grpc")]
+pub(crate) use crate::web::grpc::GrpcService;
+#[cfg(feature = "grpc")]
+pub use crate::web::grpc::{GrpcOptions, TonicService};
+#[cfg(feature = "api-poem")]
+pub use crate::web::idempotency::IdempotencyOptions;
+#[cfg(feature = "api-poem")]
+pub use crate::web::session::SessionConfig;
+#[cfg(feature = "api-poem")]
+pub use crate::web::sse::SseChannel;
+#[cfg(feature = "api-poem")]
+pub(crate) use crate::web::websocket::{self, WebSocketEndpoint, WebSocketHandler};
+#[cfg(feature = "api-poem")]
+pub use crate::web::websocket::{BroadcastHub, WebSocketOptions, WebSocketSession};
+pub(crate) use crate::workers::{ScheduledJob, Worker, Workers};
+pub use crate::workers::{WorkerContext, WorkerOptions};
 #[cfg(feature = "db-arango")]
 pub use aragog::DatabaseConnection;
+#[cfg(feature = "graphql")]
+pub use async_graphql::Executor as GraphQLExecutor;
 pub use itertools::Itertools;
-pub use north_common::{registry::service_registry::ServiceRegistry, state::NorthStateData};
+pub use north_common::{
+    registry::service_registry::ServiceRegistry,
+    state::{NorthStateData, NorthStateDataClone, NorthStateMap},
+    utils::logger_utils::LoggerOptions,
+};
 pub use std::cell::RefCell;
+pub use std::future::Future;
+pub use std::path::PathBuf;
 pub use std::rc::Rc;
 pub use std::sync::{Arc, Mutex, MutexGuard};
 #[cfg(feature = "api-poem")]
 pub use {
-    poem::{EndpointExt, IntoEndpoint, Route},
+    poem::{endpoint::BoxEndpoint, middleware::AddData, EndpointExt, IntoEndpoint, Route},
     poem_openapi::{OpenApi as PoemOpenApi, OpenApiService},
+    rust_embed::RustEmbed,
 };
 
 pub type BoxedServiceRegistry = Arc<dyn ServiceRegistry>;
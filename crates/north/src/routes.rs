@@ -0,0 +1,226 @@
+#[cfg(feature = "api-poem")]
+use poem::{endpoint::make_sync, web::Json, Route};
+use yansi::Paint;
+
+/// Where a mounted route comes from
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RouteSource {
+    Controller,
+    Handler,
+    Docs,
+    Metrics,
+    Health,
+    Admin,
+    Websocket,
+    Graphql,
+    Static,
+    Grpc,
+    /// the app passed to `custom_http_server`
+    Custom,
+}
+
+/// ## RouteInfo
+/// A route mounted on a service, as resolved after `path_prefix` and nesting
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct RouteInfo {
+    /// HTTP method, `*` when the route accepts any method
+    pub method: String,
+
+    pub path: String,
+
+    pub source: RouteSource,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation_id: Option<String>,
+
+    /// Port the route is served on, when it is not the HTTP port
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+impl RouteInfo {
+    pub fn new(method: &str, path: impl Into<String>, source: RouteSource) -> Self {
+        RouteInfo {
+            method: method.to_string(),
+            path: path.into(),
+            source,
+            operation_id: None,
+            port: None,
+        }
+    }
+
+    pub fn operation_id(mut self, operation_id: Option<&str>) -> Self {
+        self.operation_id = operation_id.map(|id| id.to_string());
+        self
+    }
+
+    pub fn port(mut self, port: Option<u16>) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// path as printed in the route table, with the port when it is not the
+    /// HTTP port
+    fn display_path(&self) -> String {
+        match self.port {
+            Some(port) => format!("{} (port {port})", self.path),
+            None => self.path.clone(),
+        }
+    }
+}
+
+/// Routes of an OpenApi controller nested at `prefix`
+#[cfg(feature = "api-poem")]
+pub(crate) fn controller_routes<T: poem_openapi::OpenApi>(prefix: &str) -> Vec<RouteInfo> {
+    let prefix = prefix.trim_end_matches('/');
+    T::meta()
+        .into_iter()
+        .flat_map(|api| api.paths)
+        .flat_map(|path| {
+            let full_path = format!("{prefix}{}", path.path);
+            path.operations.into_iter().map(move |operation| {
+                RouteInfo::new(
+                    operation.method.as_str(),
+                    full_path.clone(),
+                    RouteSource::Controller,
+                )
+                .operation_id(operation.operation_id)
+            })
+        })
+        .collect()
+}
+
+/// Serves the route table as JSON
+#[cfg(feature = "api-poem")]
+pub(crate) fn routes_endpoint(routes: Vec<RouteInfo>) -> Route {
+    Route::new().at("/", make_sync(move |_| Json(routes.clone())))
+}
+
+/// Prints the route table, sorted by path then method
+pub fn print_routes(routes: &[RouteInfo]) {
+    let mut routes = routes.to_vec();
+    routes.sort_by(|a, b| a.path.cmp(&b.path).then(a.method.cmp(&b.method)));
+
+    let method_width = routes
+        .iter()
+        .map(|r| r.method.len())
+        .max()
+        .unwrap_or(0)
+        .max(6);
+    let path_width = routes
+        .iter()
+        .map(|r| r.display_path().len())
+        .max()
+        .unwrap_or(0)
+        .max(4);
+
+    println!("{}", Paint::default("North Routes").bold().underline());
+    println!(
+        "{:<method_width$}  {:<path_width$}  {:<10}  {}",
+        Paint::green("METHOD").bold(),
+        Paint::green("PATH").bold(),
+        Paint::green("SOURCE").bold(),
+        Paint::green("OPERATION").bold(),
+    );
+    for route in routes.iter() {
+        println!(
+            "{:<method_width$}  {:<path_width$}  {:<10}  {}",
+            route.method,
+            route.display_path(),
+            format!("{:?}", route.source).to_lowercase(),
+            route.operation_id.as_deref().unwrap_or("-"),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::{
+        AdminOptions, NorthServiceBuilder, NorthServiceBuilderTrait, WebSocketSession,
+    };
+    use poem_openapi::{payload::PlainText, OpenApi};
+
+    #[derive(Clone)]
+    struct Api;
+
+    #[OpenApi]
+    impl Api {
+        #[oai(path = "/orders", method = "get", operation_id = "listOrders")]
+        async fn list(&self) -> PlainText<String> {
+            PlainText("[]".to_string())
+        }
+
+        #[oai(path = "/orders", method = "post")]
+        async fn create(&self) -> PlainText<String> {
+            PlainText("{}".to_string())
+        }
+    }
+
+    #[test]
+    fn it_lists_controller_routes_under_the_prefix() {
+        let routes = controller_routes::<Api>("/api/");
+
+        assert_eq!(
+            routes,
+            vec![
+                RouteInfo::new("GET", "/api/orders", RouteSource::Controller)
+                    .operation_id(Some("listOrders")),
+                RouteInfo::new("POST", "/api/orders", RouteSource::Controller),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_shows_the_port_of_routes_served_off_the_http_port() {
+        let route = RouteInfo::new("POST", "/helloworld.Greeter/*", RouteSource::Grpc);
+        assert_eq!(route.display_path(), "/helloworld.Greeter/*");
+        assert_eq!(
+            route.port(Some(50051)).display_path(),
+            "/helloworld.Greeter/* (port 50051)"
+        );
+    }
+
+    #[tokio::test]
+    async fn it_lists_only_the_served_routes_of_a_custom_http_server() {
+        let service = NorthServiceBuilder::default()
+            .controller(Api)
+            .custom_http_server(Route::new())
+            .handler("/hooks", make_sync(|_| "ok"))
+            .websocket("/ws", |_session: WebSocketSession| async {})
+            .with_admin(AdminOptions::default())
+            .build();
+
+        let mut expected = vec![
+            RouteInfo::new("GET", "/metrics/default", RouteSource::Metrics),
+            RouteInfo::new("*", "/*", RouteSource::Custom),
+            RouteInfo::new("GET", "/ws", RouteSource::Websocket),
+        ];
+        for path in ["/health", "/health/readiness", "/health/liveness"] {
+            expected.push(RouteInfo::new("GET", path, RouteSource::Health));
+        }
+        for path in [
+            "/_north/info",
+            "/_north/config",
+            "/_north/loggers",
+            "/_north/routes",
+        ] {
+            expected.push(RouteInfo::new("GET", path, RouteSource::Admin));
+        }
+        expected.push(RouteInfo::new(
+            "POST",
+            "/_north/loggers/:name",
+            RouteSource::Admin,
+        ));
+        assert_eq!(service.routes(), expected.as_slice());
+
+        let client = poem::test::TestClient::new(*service.poem_app);
+        client.get("/health").send().await.assert_status_is_ok();
+        client
+            .get("/_north/routes")
+            .send()
+            .await
+            .assert_status_is_ok();
+    }
+}
@@ -1,5 +1,11 @@
 use crate::prelude::*;
 
+/// Wraps the app with a typed state value so handlers can extract it as
+/// poem request data
+#[cfg(feature = "api-poem")]
+pub(crate) type DataInjector =
+    Arc<dyn Fn(BoxEndpoint<'static>) -> BoxEndpoint<'static> + Send + Sync>;
+
 /// A struct for service options. It holds the state for every created service
 #[derive(Clone)]
 pub struct NorthServiceOptions {
@@ -10,12 +16,22 @@ pub struct NorthServiceOptions {
     pub version: Option<String>,
     pub port: Option<u16>,
     pub graceful_shutdown: bool,
+    /// print the configuration and route table on startup
+    pub verbose: bool,
     pub enable_swagger: bool,
+    #[cfg(feature = "graphql")]
+    pub enable_graphiql: bool,
     pub auto_acme: bool,
     pub keep_alive: u32,
     pub read_timeout: u32,
     pub write_timeout: u32,
+    /// seconds given to in-flight requests when shutting down gracefully
+    pub shutdown_timeout: u32,
     pub registry: Option<BoxedServiceRegistry>,
+    #[cfg(feature = "api-poem")]
+    pub websocket: WebSocketOptions,
+    #[cfg(feature = "grpc")]
+    pub grpc: GrpcOptions,
 }
 
 /// default implementation for NorthServiceOptions
@@ -28,12 +44,20 @@ impl Default for NorthServiceOptions {
             version: Some("latest".to_string()),
             port: Some(5000),
             graceful_shutdown: false,
+            verbose: false,
             enable_swagger: false,
+            #[cfg(feature = "graphql")]
+            enable_graphiql: false,
             auto_acme: false,
             keep_alive: 1,
             read_timeout: 2,
             write_timeout: 2,
+            shutdown_timeout: 10,
             registry: None,
+            #[cfg(feature = "api-poem")]
+            websocket: WebSocketOptions::default(),
+            #[cfg(feature = "grpc")]
+            grpc: GrpcOptions::default(),
         }
     }
 }
@@ -43,8 +67,54 @@ pub struct NorthService {
 
     pub state_data_list: Vec<Box<dyn NorthStateData>>,
 
+    /// state data looked up by type
+    pub state: NorthStateMap,
+
+    #[cfg(feature = "api-poem")]
+    pub(crate) data_injectors: Vec<DataInjector>,
+
+    /// readiness components of the service
+    pub health: HealthIndicators,
+
+    /// notified when the service starts shutting down
+    pub shutdown: ShutdownHandle,
+
+    pub(crate) hooks: LifecycleHooks,
+
+    pub(crate) workers: Workers,
+
+    #[cfg(feature = "api-poem")]
+    pub hub: BroadcastHub,
+
+    #[cfg(feature = "api-poem")]
+    pub(crate) static_sites: Arc<Vec<StaticSite>>,
+
+    #[cfg(feature = "api-poem")]
+    pub(crate) sessions: Option<SessionConfig>,
+
+    #[cfg(feature = "api-poem")]
+    pub(crate) access_log: Option<AccessLogOptions>,
+
+    #[cfg(feature = "api-poem")]
+    pub(crate) idempotency: Option<IdempotencyOptions>,
+
+    #[cfg(feature = "grpc")]
+    pub(crate) grpc_services: Vec<GrpcService>,
+
     #[cfg(feature = "api-poem")]
     pub poem_app: Box<Route>,
+
+    pub(crate) routes: Vec<RouteInfo>,
+
+    #[cfg(feature = "db-sql")]
+    pub migrations: Option<MigrationOptions>,
+}
+
+impl NorthService {
+    /// every mounted route with its method, resolved path and source
+    pub fn routes(&self) -> &[RouteInfo] {
+        self.routes.as_slice()
+    }
 }
 
 /// NorthService struct for constructing a North service
@@ -56,9 +126,47 @@ where
 
     pub(crate) state_data_list: Vec<Box<dyn NorthStateData>>,
 
+    pub(crate) state: NorthStateMap,
+
+    #[cfg(feature = "api-poem")]
+    pub(crate) data_injectors: Vec<DataInjector>,
+
     #[cfg(feature = "api-poem")]
     pub(crate) poem_app: Route,
 
+    #[cfg(feature = "api-poem")]
+    pub(crate) handlers: Vec<String>,
+
+    #[cfg(feature = "api-poem")]
+    pub(crate) websockets: Vec<(String, WebSocketHandler)>,
+
+    #[cfg(feature = "api-poem")]
+    pub(crate) hub: BroadcastHub,
+
+    #[cfg(feature = "api-poem")]
+    pub(crate) static_sites: Vec<StaticSite>,
+
+    #[cfg(feature = "api-poem")]
+    pub(crate) sessions: Option<SessionConfig>,
+
+    #[cfg(feature = "api-poem")]
+    pub(crate) access_log: Option<AccessLogOptions>,
+
+    #[cfg(feature = "api-poem")]
+    pub(crate) idempotency: Option<IdempotencyOptions>,
+
+    #[cfg(feature = "grpc")]
+    pub(crate) grpc_services: Vec<GrpcService>,
+
+    #[cfg(feature = "graphql")]
+    pub(crate) graphql_schemas: Vec<(String, GraphQLFactory)>,
+
+    #[cfg(feature = "graphql")]
+    pub(crate) graphql_data: Vec<GraphQLDataInjector>,
+
+    #[cfg(feature = "api-poem")]
+    pub(crate) admin: Option<AdminOptions>,
+
     #[cfg(feature = "api-poem")]
     pub(crate) custom_poem_app: Option<Box<Route>>,
 
@@ -67,6 +175,17 @@ where
 
     #[cfg(feature = "db-arango")]
     pub(crate) db_connection: Option<ArcArangoConnection>,
+
+    #[cfg(feature = "db-sql")]
+    pub(crate) migrations: Option<MigrationOptions>,
+
+    pub(crate) health: HealthIndicators,
+
+    pub(crate) shutdown: ShutdownHandle,
+
+    pub(crate) hooks: LifecycleHooks,
+
+    pub(crate) workers: Workers,
 }
 
 impl<T> Default for NorthServiceBuilder<T>
@@ -79,9 +198,47 @@ where
 
             state_data_list: vec![],
 
+            state: NorthStateMap::new(),
+
+            #[cfg(feature = "api-poem")]
+            data_injectors: vec![],
+
             #[cfg(feature = "api-poem")]
             poem_app: Route::new(),
 
+            #[cfg(feature = "api-poem")]
+            handlers: vec![],
+
+            #[cfg(feature = "api-poem")]
+            websockets: vec![],
+
+            #[cfg(feature = "api-poem")]
+            hub: BroadcastHub::default(),
+
+            #[cfg(feature = "api-poem")]
+            static_sites: vec![],
+
+            #[cfg(feature = "api-poem")]
+            sessions: None,
+
+            #[cfg(feature = "api-poem")]
+            access_log: None,
+
+            #[cfg(feature = "api-poem")]
+            idempotency: None,
+
+            #[cfg(feature = "grpc")]
+            grpc_services: vec![],
+
+            #[cfg(feature = "graphql")]
+            graphql_schemas: vec![],
+
+            #[cfg(feature = "graphql")]
+            graphql_data: vec![],
+
+            #[cfg(feature = "api-poem")]
+            admin: None,
+
             #[cfg(feature = "api-poem")]
             custom_poem_app: None,
 
@@ -89,6 +246,17 @@ where
 
             #[cfg(feature = "db-arango")]
             db_connection: None,
+
+            #[cfg(feature = "db-sql")]
+            migrations: None,
+
+            health: HealthIndicators::new(),
+
+            shutdown: ShutdownHandle::new(),
+
+            hooks: LifecycleHooks::default(),
+
+            workers: Workers::default(),
         }
     }
 }
@@ -113,6 +281,113 @@ where
         );
         format!("http://{}/{}", full_addr, self.app_prefix())
     }
+
+    /// every route mounted on the app, after prefixing and nesting. A custom
+    /// http server replaces the controllers, handlers, GraphQL and docs, and
+    /// its own routes can't be listed, so it shows up as a single `/*` entry
+    #[cfg(feature = "api-poem")]
+    pub(crate) fn route_table(&self, prefix: &str) -> Vec<RouteInfo> {
+        let custom = self.custom_poem_app.is_some();
+        let mut routes = match custom {
+            true => vec![RouteInfo::new("*", "/*", RouteSource::Custom)],
+            false => controller_routes::<T>(&format!("/{prefix}")),
+        };
+
+        for path in self.handlers.iter().filter(|_| !custom) {
+            routes.push(RouteInfo::new("*", path.as_str(), RouteSource::Handler));
+        }
+        for (path, _) in self.websockets.iter() {
+            routes.push(RouteInfo::new(
+                "GET",
+                self.prefixed_path(path),
+                RouteSource::Websocket,
+            ));
+        }
+        #[cfg(feature = "graphql")]
+        for (path, _) in self.graphql_schemas.iter().filter(|_| !custom) {
+            for method in ["GET", "POST"] {
+                routes.push(RouteInfo::new(
+                    method,
+                    self.prefixed_path(path),
+                    RouteSource::Graphql,
+                ));
+            }
+        }
+        for site in self.static_sites.iter() {
+            routes.push(RouteInfo::new(
+                "GET",
+                format!("{}/*", site.mount()),
+                RouteSource::Static,
+            ));
+        }
+        #[cfg(feature = "grpc")]
+        if !self.grpc_services.is_empty() {
+            let mut names = vec![crate::web::grpc::HEALTH_SERVICE];
+            if !self.options.grpc.file_descriptor_sets.is_empty() {
+                names.push(crate::web::grpc::REFLECTION_SERVICE);
+            }
+            names.extend(self.grpc_services.iter().map(|service| service.name));
+            for name in names {
+                routes.push(
+                    RouteInfo::new("POST", format!("/{name}/*"), RouteSource::Grpc)
+                        .port(self.options.grpc.port),
+                );
+            }
+        }
+
+        if !custom {
+            routes.push(RouteInfo::new("GET", "/docs", RouteSource::Docs));
+        }
+        for path in ["/health", "/health/readiness", "/health/liveness"] {
+            routes.push(RouteInfo::new("GET", path, RouteSource::Health));
+        }
+        if self.admin.is_some() {
+            for path in [
+                "/_north/info",
+                "/_north/config",
+                "/_north/loggers",
+                "/_north/routes",
+            ] {
+                routes.push(RouteInfo::new("GET", path, RouteSource::Admin));
+            }
+            routes.push(RouteInfo::new(
+                "POST",
+                "/_north/loggers/:name",
+                RouteSource::Admin,
+            ));
+        }
+        routes
+    }
+
+    /// joins a route path to the service path prefix
+    pub(crate) fn prefixed_path(&self, path: &str) -> String {
+        let prefix = self.app_prefix();
+        let prefix = prefix.trim_end_matches('/');
+        let path = path.trim_start_matches('/');
+        if prefix.is_empty() {
+            format!("/{path}")
+        } else {
+            format!("/{prefix}/{path}")
+        }
+    }
+
+    /// mounts the websocket routes on an app
+    #[cfg(feature = "api-poem")]
+    fn mount_websockets(&self, mut app: Route, state: &NorthStateMap) -> Route {
+        for (path, handler) in self.websockets.iter() {
+            app = app.at(
+                self.prefixed_path(path),
+                WebSocketEndpoint {
+                    handler: handler.clone(),
+                    hub: self.hub.clone(),
+                    state: state.clone(),
+                    options: self.options.websocket.clone(),
+                    shutdown: self.shutdown.clone(),
+                },
+            );
+        }
+        app
+    }
 }
 
 /// implement service trait for north service
@@ -121,12 +396,14 @@ where
     T: PoemOpenApi + Clone + 'static,
 {
     #[cfg(feature = "api-poem")]
-    fn handler<E>(self, _path: impl AsRef<str>, _ep: E) -> Self
+    fn handler<E>(mut self, path: impl AsRef<str>, ep: E) -> Self
     where
         E: IntoEndpoint,
         E::Endpoint: 'static,
     {
-        // self.poem_app.nest(path, ep);
+        let path = self.prefixed_path(path.as_ref());
+        self.poem_app = std::mem::take(&mut self.poem_app).nest(path.as_str(), ep);
+        self.handlers.push(path);
         self
     }
 
@@ -160,6 +437,11 @@ where
         self
     }
 
+    fn shutdown_timeout(mut self, timeout: u32) -> Self {
+        self.options.shutdown_timeout = timeout;
+        self
+    }
+
     fn address(mut self, address: &str) -> Self {
         self.options.address = Some(address.to_string());
         self
@@ -187,11 +469,91 @@ where
         self
     }
 
+    #[cfg(feature = "graphql")]
+    fn graphql<E: GraphQLExecutor>(mut self, path: &str, schema: E) -> Self {
+        self.graphql_schemas
+            .push((path.to_string(), graphql::into_factory(schema)));
+        self
+    }
+
+    #[cfg(feature = "graphql")]
+    fn with_graphiql(mut self, enable_graphiql: bool) -> Self {
+        self.options.enable_graphiql = enable_graphiql;
+        self
+    }
+
+    #[cfg(feature = "api-poem")]
+    fn websocket<F, Fut>(mut self, path: &str, handler: F) -> Self
+    where
+        F: Fn(WebSocketSession) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.websockets
+            .push((path.to_string(), websocket::into_handler(handler)));
+        self
+    }
+
+    #[cfg(feature = "api-poem")]
+    fn websocket_options(mut self, options: WebSocketOptions) -> Self {
+        self.hub = BroadcastHub::new(options.channel_capacity);
+        self.options.websocket = options;
+        self
+    }
+
+    #[cfg(feature = "api-poem")]
+    fn static_files(
+        mut self,
+        mount: &str,
+        dir: impl Into<PathBuf>,
+        options: StaticOptions,
+    ) -> Self {
+        self.static_sites
+            .push(StaticSite::directory(mount, dir, options));
+        self
+    }
+
+    #[cfg(feature = "api-poem")]
+    fn embedded_files<E: RustEmbed>(mut self, mount: &str, options: StaticOptions) -> Self {
+        self.static_sites
+            .push(StaticSite::embedded::<E>(mount, options));
+        self
+    }
+
+    #[cfg(feature = "grpc")]
+    fn grpc_service<S: TonicService>(mut self, service: S) -> Self {
+        self.grpc_services.push(GrpcService::new(service));
+        self
+    }
+
+    #[cfg(feature = "grpc")]
+    fn grpc_options(mut self, options: GrpcOptions) -> Self {
+        self.options.grpc = options;
+        self
+    }
+
     fn with_swagger(mut self, enable_swagger: bool) -> Self {
         self.options.enable_swagger = enable_swagger;
         self
     }
 
+    #[cfg(feature = "api-poem")]
+    fn with_sessions(mut self, config: SessionConfig) -> Self {
+        self.sessions = Some(config);
+        self
+    }
+
+    #[cfg(feature = "api-poem")]
+    fn with_access_log(mut self, options: AccessLogOptions) -> Self {
+        self.access_log = Some(options);
+        self
+    }
+
+    #[cfg(feature = "api-poem")]
+    fn with_idempotency(mut self, options: IdempotencyOptions) -> Self {
+        self.idempotency = Some(options);
+        self
+    }
+
     #[cfg(feature = "db-arango")]
     fn with_database(mut self, db_connection: Arc<DatabaseConnection>) -> Self {
         self.db_connection = Some(ArcArangoConnection {
@@ -200,6 +562,25 @@ where
         self
     }
 
+    #[cfg(feature = "db-sql")]
+    fn with_migrations(mut self, options: MigrationOptions) -> Self {
+        self.migrations = Some(options);
+        self
+    }
+
+    #[cfg(feature = "api-poem")]
+    fn with_admin(mut self, options: AdminOptions) -> Self {
+        self.admin = Some(options);
+        self
+    }
+
+    fn with_logger(self, options: LoggerOptions) -> Self {
+        if let Err(e) = north_common::utils::logger_utils::init_logger_with(options) {
+            log::error!("failed to configure logging, keeping the current setup: {e}");
+        }
+        self
+    }
+
     fn with_auto_acme(mut self, enable_acme: bool) -> Self {
         self.options.auto_acme = enable_acme;
         self
@@ -213,7 +594,81 @@ where
 
     #[cfg(feature = "api-poem")]
     fn with_data<S: NorthStateData + Send + Sync + 'static>(mut self, data: S) -> Self {
-        self.state_data_list.push(Box::new(data));
+        self.state_data_list.push(data.clone_box());
+        self.state.insert(data);
+
+        // the request data and the GraphQL context share the state value
+        let shared = self
+            .state
+            .get_arc::<S>()
+            .expect("state data was just inserted");
+        let injected = shared.clone();
+        self.data_injectors.push(Arc::new(move |ep| {
+            ep.with(AddData::new(injected.clone())).boxed()
+        }));
+        #[cfg(feature = "graphql")]
+        self.graphql_data
+            .push(Arc::new(move |context| context.insert(shared.clone())));
+        self
+    }
+
+    #[cfg(feature = "api-poem")]
+    fn sse_channel<E: Clone + Send + Sync + 'static>(mut self, channel: SseChannel<E>) -> Self {
+        // kept in the state map for workers, the channel is cheap to clone so
+        // requests get the channel itself rather than an `Arc` of it
+        self.state_data_list.push(channel.clone_box());
+        self.state.insert(channel.clone());
+
+        let injected = channel.clone();
+        self.data_injectors.push(Arc::new(move |ep| {
+            ep.with(AddData::new(injected.clone())).boxed()
+        }));
+        #[cfg(feature = "graphql")]
+        self.graphql_data
+            .push(Arc::new(move |context| context.insert(channel.clone())));
+        self
+    }
+
+    fn on_start(mut self, hook: impl Into<LifecycleHook>) -> Self {
+        self.hooks.add(LifecycleStage::Start, hook.into());
+        self
+    }
+
+    fn on_ready(mut self, hook: impl Into<LifecycleHook>) -> Self {
+        self.hooks.add(LifecycleStage::Ready, hook.into());
+        self
+    }
+
+    fn on_shutdown(mut self, hook: impl Into<LifecycleHook>) -> Self {
+        self.hooks.add(LifecycleStage::Shutdown, hook.into());
+        self
+    }
+
+    fn on_stopped(mut self, hook: impl Into<LifecycleHook>) -> Self {
+        self.hooks.add(LifecycleStage::Stopped, hook.into());
+        self
+    }
+
+    fn worker<F, Fut>(mut self, name: &str, factory: F) -> Self
+    where
+        F: Fn(WorkerContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        self.workers.workers.push(Worker::new(name, factory));
+        self
+    }
+
+    fn schedule<F, Fut>(mut self, name: &str, cron: &str, job: F) -> Self
+    where
+        F: Fn(WorkerContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        self.workers.jobs.push(ScheduledJob::new(name, cron, job));
+        self
+    }
+
+    fn worker_options(mut self, options: WorkerOptions) -> Self {
+        self.workers.options = options;
         self
     }
 
@@ -222,6 +677,11 @@ where
         self
     }
 
+    fn verbose(mut self) -> Self {
+        self.options.verbose = true;
+        self
+    }
+
     #[cfg(feature = "api-poem")]
     fn build(&mut self) -> NorthService {
         // let poem_app = Route::new();
@@ -234,19 +694,74 @@ where
         let ui = api_service.swagger_ui();
         let prefix = self.app_prefix();
 
-        let c_app = std::mem::take::<Option<Box<Route>>>(&mut self.custom_poem_app);
-        let def_app = std::mem::take::<Route>(&mut self.poem_app);
+        let mut state = self.state.clone();
+        state.insert(self.hub.clone());
+
+        let mut routes = vec![RouteInfo::new(
+            "GET",
+            "/metrics/default",
+            RouteSource::Metrics,
+        )];
+        routes.extend(self.route_table(&prefix));
 
-        // println!(" len = {}", self.state_data_list.len());
+        // websocket routes are served by custom http servers as well
+        let c_app = std::mem::take::<Option<Box<Route>>>(&mut self.custom_poem_app)
+            .map(|app| Box::new(self.mount_websockets(*app, &state)));
+        let def_app = std::mem::take::<Route>(&mut self.poem_app);
+        let def_app = self.mount_websockets(def_app, &state);
+
+        #[cfg(feature = "graphql")]
+        let def_app = self
+            .graphql_schemas
+            .iter()
+            .fold(def_app, |app, (path, factory)| {
+                let path = self.prefixed_path(path);
+                app.at(
+                    path.as_str(),
+                    factory(GraphQLMount {
+                        path: path.clone(),
+                        graphiql: self.options.enable_graphiql,
+                        state: state.clone(),
+                        data: self.graphql_data.clone(),
+                    }),
+                )
+            });
+
+        // health and admin routes are served by custom http servers as well
+        let mut app = match c_app {
+            Some(app) => *app,
+            None => def_app
+                .nest(format!("/{prefix}"), api_service)
+                .nest("/docs", ui),
+        }
+        .nest("/health", health_routes(self.health.clone()));
+        if let Some(admin) = self.admin.as_ref() {
+            app = app.nest(
+                "/_north",
+                admin_routes(admin, &self.options, routes_endpoint(routes.clone())),
+            );
+        }
 
         NorthService {
             options: self.options.clone(),
             state_data_list: self.state_data_list.clone(),
-            poem_app: c_app.unwrap_or(Box::new(
-                def_app
-                    .nest(format!("/{prefix}"), api_service)
-                    .nest("/docs", ui),
-            )),
+            state,
+            data_injectors: self.data_injectors.clone(),
+            health: self.health.clone(),
+            shutdown: self.shutdown.clone(),
+            hooks: self.hooks.clone(),
+            workers: self.workers.clone(),
+            hub: self.hub.clone(),
+            static_sites: Arc::new(self.static_sites.clone()),
+            sessions: self.sessions.clone(),
+            access_log: self.access_log.clone(),
+            idempotency: self.idempotency.clone(),
+            #[cfg(feature = "grpc")]
+            grpc_services: self.grpc_services.clone(),
+            routes,
+            poem_app: Box::new(app),
+            #[cfg(feature = "db-sql")]
+            migrations: self.migrations.take(),
         }
     }
 
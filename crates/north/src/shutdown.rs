@@ -0,0 +1,47 @@
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// ## ShutdownHandle
+/// Notifies long-lived tasks, such as websocket sessions, that the service
+/// received a shutdown signal.
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    sender: Arc<watch::Sender<bool>>,
+    receiver: watch::Receiver<bool>,
+}
+
+impl Default for ShutdownHandle {
+    fn default() -> Self {
+        let (sender, receiver) = watch::channel(false);
+        ShutdownHandle {
+            sender: Arc::new(sender),
+            receiver,
+        }
+    }
+}
+
+impl ShutdownHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// starts the shutdown
+    pub fn trigger(&self) {
+        let _ = self.sender.send(true);
+    }
+
+    /// returns true once shutdown has started
+    pub fn is_triggered(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    /// resolves when shutdown starts
+    pub async fn wait(&self) {
+        let mut receiver = self.receiver.clone();
+        while !*receiver.borrow() {
+            if receiver.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
@@ -3,7 +3,6 @@ use crate::NorthServiceOptions;
 use north_common::utils::logger_utils::print_format;
 use yansi::Paint;
 
-#[allow(dead_code)]
 pub(crate) async fn shutdown_signal() {
     tokio::signal::ctrl_c()
         .await
@@ -12,7 +11,6 @@ pub(crate) async fn shutdown_signal() {
 
 pub type NorthResult<T> = Result<T, Error>;
 
-#[allow(dead_code)]
 pub fn print_server_info(opts: &NorthServiceOptions) {
     println!(
         "{}",
@@ -34,10 +32,6 @@ pub fn print_server_info(opts: &NorthServiceOptions) {
         "write timeout",
         format!("{}{}", opts.write_timeout.to_string().as_str(), "s").as_str(),
     );
-    print_format(
-        "write timeout",
-        format!("{}{}", opts.write_timeout.to_string().as_str(), "s").as_str(),
-    );
     if opts.graceful_shutdown {
         print_format("graceful shutdown", "enabled");
     } else {
@@ -0,0 +1,314 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use log::Level;
+use north_common::utils::logger_utils::REQUESTS_LOGGER;
+use poem::http::{header, HeaderMap, StatusCode};
+use poem::{Endpoint, IntoResponse, PathPattern, Request, Response};
+use serde_json::json;
+
+/// Layout of an access log line
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AccessLogFormat {
+    /// NCSA Common Log Format
+    Common,
+
+    /// Common Log Format followed by the referer and user agent
+    #[default]
+    Combined,
+
+    /// One JSON object per request
+    Json,
+}
+
+/// ## AccessLogOptions
+/// Options of the access log enabled with `with_access_log`. Records are
+/// written to the `north::requests` logger, which goes to the `requests` log
+/// file when one is configured
+#[derive(Clone, Debug)]
+pub struct AccessLogOptions {
+    /// @defaults to Combined
+    pub format: AccessLogFormat,
+
+    /// Share of requests logged, between `0.0` and `1.0`. Server errors and
+    /// slow requests are always logged
+    ///
+    /// @defaults to 1.0
+    pub sample_rate: f64,
+
+    /// Requests taking longer are logged as warnings
+    ///
+    /// @defaults to [None]
+    pub slow_threshold: Option<Duration>,
+
+    /// Path prefixes never logged
+    ///
+    /// @defaults to /health, /metrics
+    pub exclude_paths: Vec<String>,
+
+    /// Header carrying the request ID, read from the request then the response
+    ///
+    /// @defaults to x-request-id
+    pub request_id_header: String,
+}
+
+impl Default for AccessLogOptions {
+    fn default() -> Self {
+        AccessLogOptions {
+            format: AccessLogFormat::default(),
+            sample_rate: 1.0,
+            slow_threshold: None,
+            exclude_paths: vec!["/health".to_string(), "/metrics".to_string()],
+            request_id_header: "x-request-id".to_string(),
+        }
+    }
+}
+
+impl AccessLogOptions {
+    pub fn format(mut self, format: AccessLogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn sample_rate(mut self, sample_rate: f64) -> Self {
+        self.sample_rate = sample_rate.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn slow_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_threshold = Some(threshold);
+        self
+    }
+
+    pub fn exclude_path(mut self, path: &str) -> Self {
+        self.exclude_paths.push(path.to_string());
+        self
+    }
+
+    fn excluded(&self, path: &str) -> bool {
+        self.exclude_paths.iter().any(|prefix| {
+            path.strip_prefix(prefix.as_str())
+                .map(|rest| rest.is_empty() || rest.starts_with('/'))
+                .unwrap_or(false)
+        })
+    }
+}
+
+/// A request as written to the access log
+#[derive(Debug)]
+struct AccessRecord {
+    method: String,
+    path: String,
+    template: Option<String>,
+    version: String,
+    status: StatusCode,
+    duration: Duration,
+    bytes: Option<u64>,
+    remote_addr: String,
+    request_id: Option<String>,
+    referer: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl AccessRecord {
+    fn format(&self, format: AccessLogFormat) -> String {
+        let time = chrono::Local::now();
+        let bytes = self
+            .bytes
+            .map(|bytes| bytes.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let common = format!(
+            "{} - - [{}] \"{} {} {}\" {} {}",
+            self.remote_addr,
+            time.format("%d/%b/%Y:%H:%M:%S %z"),
+            self.method,
+            self.path,
+            self.version,
+            self.status.as_u16(),
+            bytes,
+        );
+
+        match format {
+            AccessLogFormat::Common => common,
+            AccessLogFormat::Combined => format!(
+                "{common} \"{}\" \"{}\" {}ms{}",
+                self.referer.as_deref().unwrap_or("-"),
+                self.user_agent.as_deref().unwrap_or("-"),
+                self.duration.as_millis(),
+                self.request_id
+                    .as_deref()
+                    .map(|id| format!(" {id}"))
+                    .unwrap_or_default(),
+            ),
+            AccessLogFormat::Json => json!({
+                "time": time.to_rfc3339(),
+                "method": self.method,
+                "path": self.path,
+                "template": self.template,
+                "version": self.version,
+                "status": self.status.as_u16(),
+                "duration_ms": self.duration.as_secs_f64() * 1000.0,
+                "bytes": self.bytes,
+                "remote_addr": self.remote_addr,
+                "request_id": self.request_id,
+                "referer": self.referer,
+                "user_agent": self.user_agent,
+            })
+            .to_string(),
+        }
+    }
+}
+
+/// Logs every request that is not excluded or sampled out
+pub(crate) struct AccessLog<E> {
+    inner: E,
+    options: AccessLogOptions,
+    sampler: Sampler,
+}
+
+impl<E> AccessLog<E> {
+    pub(crate) fn new(inner: E, options: AccessLogOptions) -> Self {
+        AccessLog {
+            inner,
+            options,
+            sampler: Sampler::default(),
+        }
+    }
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for AccessLog<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        if self.options.excluded(req.uri().path()) {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        }
+
+        let header_value = |headers: &HeaderMap, name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let method = req.method().to_string();
+        let path = req.original_uri().to_string();
+        let version = format!("{:?}", req.version());
+        let remote_addr = req.remote_addr().to_string();
+        let request_id = header_value(req.headers(), &self.options.request_id_header);
+        let referer = header_value(req.headers(), header::REFERER.as_str());
+        let user_agent = header_value(req.headers(), header::USER_AGENT.as_str());
+
+        let started = Instant::now();
+        let response = match self.inner.call(req).await {
+            Ok(response) => response.into_response(),
+            Err(e) => e.into_response(),
+        };
+        let duration = started.elapsed();
+
+        let slow = self
+            .options
+            .slow_threshold
+            .map(|threshold| duration >= threshold)
+            .unwrap_or(false);
+        let status = response.status();
+        if !slow && !status.is_server_error() && !self.sampler.sample(self.options.sample_rate) {
+            return Ok(response);
+        }
+
+        let record = AccessRecord {
+            method,
+            path,
+            template: response.data::<PathPattern>().map(|p| p.0.to_string()),
+            version,
+            status,
+            duration,
+            bytes: header_value(response.headers(), header::CONTENT_LENGTH.as_str())
+                .and_then(|length| length.parse().ok()),
+            remote_addr,
+            request_id: request_id
+                .or_else(|| header_value(response.headers(), &self.options.request_id_header)),
+            referer,
+            user_agent,
+        };
+        let level = if slow { Level::Warn } else { Level::Info };
+        log::log!(target: REQUESTS_LOGGER, level, "{}", record.format(self.options.format));
+
+        Ok(response)
+    }
+}
+
+/// Picks requests at random, without pulling in a RNG
+#[derive(Default)]
+struct Sampler {
+    hasher: RandomState,
+    counter: AtomicU64,
+}
+
+impl Sampler {
+    fn sample(&self, rate: f64) -> bool {
+        if rate >= 1.0 {
+            return true;
+        }
+        let mut hasher = self.hasher.build_hasher();
+        hasher.write_u64(self.counter.fetch_add(1, Ordering::Relaxed));
+        (hasher.finish() as f64 / u64::MAX as f64) < rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> AccessRecord {
+        AccessRecord {
+            method: "GET".to_string(),
+            path: "/api/orders/42?full=true".to_string(),
+            template: Some("/api/orders/:id".to_string()),
+            version: "HTTP/1.1".to_string(),
+            status: StatusCode::OK,
+            duration: Duration::from_millis(12),
+            bytes: Some(512),
+            remote_addr: "127.0.0.1:52100".to_string(),
+            request_id: Some("req-1".to_string()),
+            referer: None,
+            user_agent: Some("curl/8.0".to_string()),
+        }
+    }
+
+    #[test]
+    fn it_formats_combined_and_json_records() {
+        let line = record().format(AccessLogFormat::Combined);
+        assert!(line.starts_with("127.0.0.1:52100 - - ["));
+        assert!(line.ends_with(
+            "\"GET /api/orders/42?full=true HTTP/1.1\" 200 512 \"-\" \"curl/8.0\" 12ms req-1"
+        ));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&record().format(AccessLogFormat::Json)).unwrap();
+        assert_eq!(json["template"], "/api/orders/:id");
+        assert_eq!(json["status"], 200);
+        assert_eq!(json["request_id"], "req-1");
+    }
+
+    #[test]
+    fn it_excludes_paths_by_segment() {
+        let options = AccessLogOptions::default();
+        assert!(options.excluded("/health"));
+        assert!(options.excluded("/health/readiness"));
+        assert!(!options.excluded("/healthy"));
+        assert!(!options.excluded("/api/orders"));
+    }
+
+    #[test]
+    fn it_samples_the_configured_share() {
+        let sampler = Sampler::default();
+        assert!((0..100).all(|_| sampler.sample(1.0)));
+        assert!((0..100).all(|_| !sampler.sample(0.0)));
+
+        let sampled = (0..10_000).filter(|_| sampler.sample(0.25)).count();
+        assert!((2_000..3_000).contains(&sampled));
+    }
+}
@@ -0,0 +1,434 @@
+use std::borrow::Cow;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use percent_encoding::percent_decode_str;
+use poem::http::{header, HeaderMap, Method, StatusCode};
+use poem::{Endpoint, IntoResponse, Request, Response};
+use rust_embed::{EmbeddedFile, RustEmbed};
+
+/// # StaticOptions
+///
+/// Options of a static file mount
+#[derive(Clone, Debug)]
+pub struct StaticOptions {
+    /// File served for the mount root and directory paths
+    ///
+    /// @defaults to index.html
+    pub index_file: String,
+
+    /// Serve the index file for unknown paths without a file extension, so
+    /// client side routes of single-page apps can be reloaded
+    ///
+    /// @defaults to false
+    pub spa_fallback: bool,
+
+    /// Cache-Control header of assets. The index file is always served with
+    /// `no-cache` so new deployments are picked up
+    ///
+    /// @defaults to public, max-age=3600
+    pub cache_control: Option<String>,
+
+    /// Serve `.br` and `.gz` files next to an asset when the client accepts
+    /// that encoding
+    ///
+    /// @defaults to true
+    pub precompressed: bool,
+}
+
+impl Default for StaticOptions {
+    fn default() -> Self {
+        StaticOptions {
+            index_file: "index.html".to_string(),
+            spa_fallback: false,
+            cache_control: Some("public, max-age=3600".to_string()),
+            precompressed: true,
+        }
+    }
+}
+
+impl StaticOptions {
+    /// Options for a single-page app: index fallback enabled
+    pub fn spa() -> Self {
+        StaticOptions {
+            spa_fallback: true,
+            ..Default::default()
+        }
+    }
+}
+
+struct StaticFile {
+    data: Cow<'static, [u8]>,
+    etag: String,
+}
+
+#[derive(Clone)]
+enum StaticSource {
+    Directory(PathBuf),
+    Embedded(fn(&str) -> Option<EmbeddedFile>),
+}
+
+impl StaticSource {
+    async fn get(&self, path: &str) -> Option<StaticFile> {
+        match self {
+            StaticSource::Directory(root) => {
+                let path = root.join(path);
+                let metadata = tokio::fs::metadata(&path).await.ok()?;
+                if !metadata.is_file() {
+                    return None;
+                }
+                let modified = metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                    .map(|time| time.as_nanos())
+                    .unwrap_or_default();
+                let data = tokio::fs::read(&path).await.ok()?;
+                Some(StaticFile {
+                    etag: format!("\"{:x}-{:x}\"", data.len(), modified),
+                    data: Cow::Owned(data),
+                })
+            }
+            StaticSource::Embedded(get) => {
+                let file = get(path)?;
+                let hash: String = file
+                    .metadata
+                    .sha256_hash()
+                    .iter()
+                    .map(|b| format!("{b:02x}"))
+                    .collect();
+                Some(StaticFile {
+                    data: file.data,
+                    etag: format!("\"{hash}\""),
+                })
+            }
+        }
+    }
+}
+
+/// Files served at a mount point, from a directory or embedded in the binary
+#[derive(Clone)]
+pub(crate) struct StaticSite {
+    mount: String,
+    source: StaticSource,
+    options: StaticOptions,
+}
+
+impl StaticSite {
+    pub(crate) fn directory(mount: &str, dir: impl Into<PathBuf>, options: StaticOptions) -> Self {
+        StaticSite {
+            mount: normalize_mount(mount),
+            source: StaticSource::Directory(dir.into()),
+            options,
+        }
+    }
+
+    pub(crate) fn embedded<E: RustEmbed>(mount: &str, options: StaticOptions) -> Self {
+        StaticSite {
+            mount: normalize_mount(mount),
+            source: StaticSource::Embedded(E::get),
+            options,
+        }
+    }
+
+    /// Mount point of the files, empty when mounted at the root
+    pub(crate) fn mount(&self) -> &str {
+        self.mount.as_str()
+    }
+
+    /// Path of the requested file relative to the mount, or `None` when the
+    /// request is outside the mount or tries to escape it
+    fn relative_path(&self, path: &str) -> Option<String> {
+        let rest = path.strip_prefix(self.mount.as_str())?;
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+
+        let decoded = percent_decode_str(rest).decode_utf8().ok()?;
+        let mut segments = vec![];
+        for segment in decoded.split('/') {
+            match segment {
+                "" | "." => continue,
+                ".." => return None,
+                s if s.contains('\\') || s.contains('\0') => return None,
+                s => segments.push(s),
+            }
+        }
+
+        let mut relative = segments.join("/");
+        if relative.is_empty() || decoded.ends_with('/') {
+            if !relative.is_empty() {
+                relative.push('/');
+            }
+            relative.push_str(&self.options.index_file);
+        }
+        Some(relative)
+    }
+
+    async fn serve(&self, method: &Method, path: &str, headers: &HeaderMap) -> Option<Response> {
+        let relative = self.relative_path(path)?;
+
+        if let Some(response) = self.serve_file(method, &relative, headers).await {
+            return Some(response);
+        }
+
+        let last_segment = relative.rsplit('/').next().unwrap_or_default();
+        if self.options.spa_fallback && !last_segment.contains('.') {
+            let index = self.options.index_file.clone();
+            return self.serve_file(method, &index, headers).await;
+        }
+        None
+    }
+
+    async fn serve_file(
+        &self,
+        method: &Method,
+        path: &str,
+        headers: &HeaderMap,
+    ) -> Option<Response> {
+        let mut encoding = None;
+        let mut file = None;
+        if self.options.precompressed {
+            for (name, extension) in [("br", "br"), ("gzip", "gz")] {
+                if !accepts_encoding(headers, name) {
+                    continue;
+                }
+                if let Some(compressed) = self.source.get(&format!("{path}.{extension}")).await {
+                    encoding = Some(name);
+                    file = Some(compressed);
+                    break;
+                }
+            }
+        }
+        let file = match file {
+            Some(file) => file,
+            None => self.source.get(path).await?,
+        };
+
+        let etag = match encoding {
+            Some(encoding) => format!("{}-{encoding}\"", file.etag.trim_end_matches('"')),
+            None => file.etag,
+        };
+        let cache_control = if path.rsplit('/').next() == Some(self.options.index_file.as_str()) {
+            Some("no-cache".to_string())
+        } else {
+            self.options.cache_control.clone()
+        };
+
+        let mut builder = Response::builder().header(header::ETAG, etag.as_str());
+        if let Some(cache_control) = cache_control {
+            builder = builder.header(header::CACHE_CONTROL, cache_control);
+        }
+        if self.options.precompressed {
+            builder = builder.header(header::VARY, "Accept-Encoding");
+        }
+
+        if matches_etag(headers, &etag) {
+            return Some(builder.status(StatusCode::NOT_MODIFIED).finish());
+        }
+
+        let mime = mime_guess::from_path(path).first_or_octet_stream();
+        builder = builder
+            .header(header::CONTENT_TYPE, mime.as_ref())
+            .header(header::CONTENT_LENGTH, file.data.len());
+        if let Some(encoding) = encoding {
+            builder = builder.header(header::CONTENT_ENCODING, encoding);
+        }
+
+        if method == Method::HEAD {
+            return Some(builder.finish());
+        }
+        Some(builder.body(file.data.into_owned()))
+    }
+}
+
+/// Serves static files for GET and HEAD requests the app answered with
+/// `404`. API routes are always tried first and paths under the API prefix
+/// are never answered with static files.
+pub(crate) struct StaticFallback<E> {
+    inner: E,
+    sites: Arc<Vec<StaticSite>>,
+    api_prefix: String,
+}
+
+impl<E> StaticFallback<E> {
+    pub(crate) fn new(inner: E, sites: Arc<Vec<StaticSite>>, path_prefix: Option<&str>) -> Self {
+        StaticFallback {
+            inner,
+            sites,
+            api_prefix: normalize_mount(path_prefix.unwrap_or_default()),
+        }
+    }
+
+    fn is_api_path(&self, path: &str) -> bool {
+        if self.api_prefix.is_empty() {
+            return false;
+        }
+        match path.strip_prefix(self.api_prefix.as_str()) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for StaticFallback<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        let method = req.method().clone();
+        if !(method == Method::GET || method == Method::HEAD) || self.is_api_path(req.uri().path())
+        {
+            return self.inner.call(req).await.map(IntoResponse::into_response);
+        }
+
+        let path = req.uri().path().to_string();
+        let headers = req.headers().clone();
+        let result = self.inner.call(req).await.map(IntoResponse::into_response);
+
+        let not_found = match &result {
+            Ok(response) => response.status() == StatusCode::NOT_FOUND,
+            Err(err) => err.status() == StatusCode::NOT_FOUND,
+        };
+        if !not_found {
+            return result;
+        }
+
+        for site in self.sites.iter() {
+            if let Some(response) = site.serve(&method, &path, &headers).await {
+                return Ok(response);
+            }
+        }
+        result
+    }
+}
+
+/// Turns `/app/` or `app` into `/app`, and `/` into an empty mount
+fn normalize_mount(mount: &str) -> String {
+    let mount = mount.trim_matches('/');
+    if mount.is_empty() {
+        String::new()
+    } else {
+        format!("/{mount}")
+    }
+}
+
+fn accepts_encoding(headers: &HeaderMap, encoding: &str) -> bool {
+    headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|item| {
+            let mut parts = item.split(';');
+            let name = parts.next().unwrap_or_default().trim();
+            let rejected = parts.any(|param| {
+                param
+                    .trim()
+                    .strip_prefix("q=")
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .map(|q| q == 0.0)
+                    .unwrap_or(false)
+            });
+            name.eq_ignore_ascii_case(encoding) && !rejected
+        })
+}
+
+fn matches_etag(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|tag| tag.trim().trim_start_matches("W/"))
+        .any(|tag| tag == etag || tag == "*")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn site(dir: &Path, options: StaticOptions) -> StaticSite {
+        StaticSite::directory("/app/", dir, options)
+    }
+
+    fn temp_site(name: &str, options: StaticOptions) -> (StaticSite, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("north-static-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(dir.join("index.html"), "<html></html>").unwrap();
+        std::fs::write(dir.join("assets/app.js"), "console.log(1)").unwrap();
+        std::fs::write(dir.join("assets/app.js.br"), "compressed").unwrap();
+        (site(&dir, options), dir)
+    }
+
+    #[test]
+    fn it_resolves_paths_inside_the_mount() {
+        let site = site(Path::new("dist"), StaticOptions::default());
+
+        assert_eq!(site.relative_path("/app").as_deref(), Some("index.html"));
+        assert_eq!(
+            site.relative_path("/app/docs/").as_deref(),
+            Some("docs/index.html")
+        );
+        assert_eq!(
+            site.relative_path("/app/a%20b.js").as_deref(),
+            Some("a b.js")
+        );
+        assert_eq!(site.relative_path("/app/../secret"), None);
+        assert_eq!(site.relative_path("/app/%2e%2e/secret"), None);
+        assert_eq!(site.relative_path("/application"), None);
+    }
+
+    #[tokio::test]
+    async fn it_serves_precompressed_files_with_etag() {
+        let (site, dir) = temp_site("precompressed", StaticOptions::default());
+        let mut headers = HeaderMap::new();
+        headers.insert(header::ACCEPT_ENCODING, "gzip, br".parse().unwrap());
+
+        let response = site
+            .serve(&Method::GET, "/app/assets/app.js", &headers)
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers().get(header::CONTENT_ENCODING).unwrap(),
+            "br"
+        );
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/javascript"
+        );
+
+        let etag = response.headers().get(header::ETAG).unwrap().clone();
+        headers.insert(header::IF_NONE_MATCH, etag);
+        let response = site
+            .serve(&Method::GET, "/app/assets/app.js", &headers)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_falls_back_to_index_for_spa_routes() {
+        let (site, dir) = temp_site("spa", StaticOptions::spa());
+        let headers = HeaderMap::new();
+
+        let response = site
+            .serve(&Method::GET, "/app/orders/42", &headers)
+            .await
+            .unwrap();
+        assert_eq!(
+            response.headers().get(header::CACHE_CONTROL).unwrap(),
+            "no-cache"
+        );
+        assert!(site
+            .serve(&Method::GET, "/app/missing.png", &headers)
+            .await
+            .is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
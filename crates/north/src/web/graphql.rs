@@ -0,0 +1,166 @@
+use std::sync::Arc;
+
+use async_graphql::http::{GraphiQLSource, ALL_WEBSOCKET_PROTOCOLS};
+use async_graphql::{BatchRequest, Data, Executor};
+use async_graphql_poem::{
+    GraphQLBatchRequest, GraphQLBatchResponse, GraphQLProtocol, GraphQLWebSocket,
+};
+use north_common::state::NorthStateMap;
+use poem::endpoint::BoxEndpoint;
+use poem::http::{header, Method};
+use poem::web::websocket::WebSocket;
+use poem::web::Html;
+use poem::{Endpoint, EndpointExt, FromRequest, IntoResponse, Request, Response};
+
+/// Adds a state value to the data of a GraphQL request
+pub(crate) type GraphQLDataInjector = Arc<dyn Fn(&mut Data) + Send + Sync>;
+
+/// Creates the endpoint of a schema once the service is built
+pub(crate) type GraphQLFactory = Arc<dyn Fn(GraphQLMount) -> BoxEndpoint<'static> + Send + Sync>;
+
+/// Everything a GraphQL endpoint needs from the service
+#[derive(Clone)]
+pub(crate) struct GraphQLMount {
+    pub(crate) path: String,
+    pub(crate) graphiql: bool,
+    pub(crate) state: NorthStateMap,
+    pub(crate) data: Vec<GraphQLDataInjector>,
+}
+
+impl GraphQLMount {
+    fn context_data(&self) -> Data {
+        let mut data = Data::default();
+        data.insert(self.state.clone());
+        for inject in self.data.iter() {
+            inject(&mut data);
+        }
+        data
+    }
+
+    fn inject(&self, mut batch: BatchRequest) -> BatchRequest {
+        for request in batch.iter_mut() {
+            request.data.insert(self.state.clone());
+            for inject in self.data.iter() {
+                inject(&mut request.data);
+            }
+        }
+        batch
+    }
+}
+
+pub(crate) fn into_factory<E: Executor>(executor: E) -> GraphQLFactory {
+    Arc::new(move |mount| {
+        GraphQLEndpoint {
+            executor: executor.clone(),
+            mount,
+        }
+        .boxed()
+    })
+}
+
+/// Serves queries and mutations over POST and GET, subscriptions over
+/// websocket and GraphiQL when enabled
+struct GraphQLEndpoint<E> {
+    executor: E,
+    mount: GraphQLMount,
+}
+
+#[poem::async_trait]
+impl<E: Executor> Endpoint for GraphQLEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        let (req, mut body) = req.split();
+
+        if req.method() == Method::GET && req.headers().contains_key(header::UPGRADE) {
+            let websocket = WebSocket::from_request(&req, &mut body).await?;
+            let protocol = GraphQLProtocol::from_request(&req, &mut body).await?;
+            let executor = self.executor.clone();
+            let data = self.mount.context_data();
+
+            return Ok(websocket
+                .protocols(ALL_WEBSOCKET_PROTOCOLS)
+                .on_upgrade(move |stream| {
+                    GraphQLWebSocket::new(stream, executor, protocol)
+                        .with_data(data)
+                        .serve()
+                })
+                .into_response());
+        }
+
+        if req.method() == Method::GET && self.mount.graphiql && req.uri().query().is_none() {
+            let source = GraphiQLSource::build()
+                .endpoint(&self.mount.path)
+                .subscription_endpoint(&self.mount.path)
+                .finish();
+            return Ok(Html(source).into_response());
+        }
+
+        let batch = GraphQLBatchRequest::from_request(&req, &mut body).await?;
+        let batch = self.mount.inject(batch.0);
+        Ok(GraphQLBatchResponse(self.executor.execute_batch(batch).await).into_response())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_graphql::{Context, EmptyMutation, EmptySubscription, Object, Schema};
+    use poem::test::TestClient;
+
+    #[derive(Clone)]
+    struct Greeting(String);
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        async fn greeting(&self, ctx: &Context<'_>) -> String {
+            ctx.data_unchecked::<Greeting>().0.clone()
+        }
+    }
+
+    fn endpoint(graphiql: bool) -> BoxEndpoint<'static> {
+        let greeting = Greeting("hello".to_string());
+        into_factory(Schema::new(Query, EmptyMutation, EmptySubscription))(GraphQLMount {
+            path: "/graphql".to_string(),
+            graphiql,
+            state: NorthStateMap::new(),
+            data: vec![Arc::new(move |data: &mut Data| {
+                data.insert(greeting.clone())
+            })],
+        })
+    }
+
+    #[tokio::test]
+    async fn it_exposes_state_data_to_resolvers() {
+        let client = TestClient::new(endpoint(false));
+        let response = client
+            .post("/graphql")
+            .body_json(&serde_json::json!({ "query": "{ greeting }" }))
+            .send()
+            .await;
+
+        response.assert_status_is_ok();
+        response
+            .assert_json(&serde_json::json!({ "data": { "greeting": "hello" } }))
+            .await;
+    }
+
+    #[tokio::test]
+    async fn it_only_serves_graphiql_when_enabled() {
+        let response = TestClient::new(endpoint(true)).get("/graphql").send().await;
+        response.assert_content_type("text/html; charset=utf-8");
+
+        // without GraphiQL, a GET without a query is a GraphQL request
+        let response = TestClient::new(endpoint(false))
+            .get("/graphql")
+            .send()
+            .await;
+        let content_type = response.0.headers().get(header::CONTENT_TYPE);
+        assert_ne!(
+            content_type.and_then(|value| value.to_str().ok()),
+            Some("text/html; charset=utf-8")
+        );
+    }
+}
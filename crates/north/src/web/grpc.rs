@@ -0,0 +1,178 @@
+use std::convert::Infallible;
+use std::sync::Arc;
+
+use poem::endpoint::{BoxEndpoint, TowerCompatExt};
+use poem::http::header;
+use poem::{Endpoint, EndpointExt, IntoResponse, Request, Response, Route};
+use tonic::body::BoxBody;
+use tonic::codegen::{http, BoxFuture, Service};
+use tonic::server::NamedService;
+use tonic::transport::Body;
+use tonic_health::server::HealthReporter;
+use tonic_health::ServingStatus;
+
+use crate::error::Error;
+
+pub(crate) const HEALTH_SERVICE: &str = "grpc.health.v1.Health";
+pub(crate) const REFLECTION_SERVICE: &str = "grpc.reflection.v1alpha.ServerReflection";
+
+/// # GrpcOptions
+///
+/// Options of the gRPC services hosted by a North service
+#[derive(Clone, Debug, Default)]
+pub struct GrpcOptions {
+    /// Serve gRPC on its own port. When `None`, gRPC requests are detected by
+    /// their `application/grpc` content type and served on the HTTP port
+    ///
+    /// @defaults to [None]
+    pub port: Option<u16>,
+
+    /// Encoded file descriptor sets registered with the reflection service,
+    /// usually `tonic::include_file_descriptor_set!`. Reflection is only
+    /// served when at least one set is given
+    ///
+    /// @defaults to empty
+    pub file_descriptor_sets: Vec<&'static [u8]>,
+}
+
+/// A tonic server, as generated by `tonic-build`, that can be mounted on a
+/// North service
+pub trait TonicService:
+    Service<
+        http::Request<Body>,
+        Response = http::Response<BoxBody>,
+        Error = Infallible,
+        Future = BoxFuture<http::Response<BoxBody>, Infallible>,
+    > + NamedService
+    + Clone
+    + Send
+    + Sync
+    + 'static
+{
+}
+
+impl<S> TonicService for S where
+    S: Service<
+            http::Request<Body>,
+            Response = http::Response<BoxBody>,
+            Error = Infallible,
+            Future = BoxFuture<http::Response<BoxBody>, Infallible>,
+        > + NamedService
+        + Clone
+        + Send
+        + Sync
+        + 'static
+{
+}
+
+/// A tonic service mounted under its fully qualified name
+#[derive(Clone)]
+pub(crate) struct GrpcService {
+    pub(crate) name: &'static str,
+    pub(crate) endpoint: Arc<dyn Fn() -> BoxEndpoint<'static> + Send + Sync>,
+}
+
+impl GrpcService {
+    pub(crate) fn new<S: TonicService>(service: S) -> Self {
+        GrpcService {
+            name: S::NAME,
+            endpoint: Arc::new(move || service.clone().compat().map_to_response().boxed()),
+        }
+    }
+}
+
+/// Serving status of the gRPC services reported by the health service
+pub(crate) struct GrpcHealth {
+    reporter: HealthReporter,
+    names: Vec<&'static str>,
+}
+
+/// Routes every gRPC service plus the health and reflection services
+pub(crate) fn grpc_routes(
+    services: &[GrpcService],
+    options: &GrpcOptions,
+) -> Result<(Route, GrpcHealth), Error> {
+    let (reporter, health) = tonic_health::server::health_reporter();
+    let mut route = Route::new().nest_no_strip(format!("/{HEALTH_SERVICE}"), health.compat());
+
+    if !options.file_descriptor_sets.is_empty() {
+        let mut builder = tonic_reflection::server::Builder::configure();
+        for set in options.file_descriptor_sets.iter() {
+            builder = builder.register_encoded_file_descriptor_set(*set);
+        }
+        let reflection = builder
+            .build()
+            .map_err(|e| Error::InternalServerError(e.to_string()))?;
+        route = route.nest_no_strip(format!("/{REFLECTION_SERVICE}"), reflection.compat());
+    }
+
+    for service in services.iter() {
+        route = route.nest_no_strip(format!("/{}", service.name), (service.endpoint)());
+    }
+
+    let health = GrpcHealth {
+        reporter,
+        names: services.iter().map(|service| service.name).collect(),
+    };
+    Ok((route, health))
+}
+
+impl GrpcHealth {
+    /// Reports every service, and the server as a whole, as serving or not
+    pub(crate) async fn set_serving(&mut self, serving: bool) {
+        let status = if serving {
+            ServingStatus::Serving
+        } else {
+            ServingStatus::NotServing
+        };
+        self.reporter.set_service_status("", status).await;
+        for name in self.names.iter() {
+            self.reporter.set_service_status(*name, status).await;
+        }
+    }
+}
+
+/// Sends requests with a gRPC content type to the gRPC routes and everything
+/// else to the HTTP app
+pub(crate) struct GrpcDispatch<E> {
+    pub(crate) http: E,
+    pub(crate) grpc: BoxEndpoint<'static>,
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for GrpcDispatch<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        if is_grpc(&req) {
+            return self.grpc.call(req).await;
+        }
+        self.http.call(req).await.map(IntoResponse::into_response)
+    }
+}
+
+fn is_grpc(req: &Request) -> bool {
+    req.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.starts_with("application/grpc"))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_detects_grpc_requests() {
+        let grpc = Request::builder()
+            .header(header::CONTENT_TYPE, "application/grpc+proto")
+            .finish();
+        let json = Request::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .finish();
+
+        assert!(is_grpc(&grpc));
+        assert!(!is_grpc(&json));
+    }
+}
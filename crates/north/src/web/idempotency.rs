@@ -0,0 +1,506 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use poem::http::{HeaderName, HeaderValue, Method, StatusCode};
+use poem::{Endpoint, IntoResponse, Request, Response};
+
+use crate::error::Error;
+
+/// Header added to replayed responses
+const REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// A response kept to be replayed
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl StoredResponse {
+    fn into_response(self) -> Response {
+        let mut response = Response::builder()
+            .status(StatusCode::from_u16(self.status).unwrap_or(StatusCode::OK))
+            .body(self.body);
+        for (name, value) in self.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(&value),
+            ) {
+                response.headers_mut().append(name, value);
+            }
+        }
+        response
+            .headers_mut()
+            .insert(REPLAYED_HEADER, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// Outcome of claiming an idempotency key
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IdempotencyClaim {
+    /// The key is new, the request runs and its response is stored
+    Acquired,
+
+    /// A request with the same key is still running
+    InProgress,
+
+    /// A request with the same key completed
+    Completed {
+        fingerprint: String,
+        response: StoredResponse,
+    },
+}
+
+/// Storage of idempotency keys. Implementations must claim keys atomically,
+/// so that only one of several concurrent requests gets
+/// [IdempotencyClaim::Acquired]
+#[async_trait::async_trait]
+pub trait IdempotencyStore: Send + Sync {
+    /// Claims `key` for a request with the given fingerprint, or returns the
+    /// state of the request that claimed it first. The claim expires after
+    /// `ttl` if it is never completed nor released
+    async fn claim(
+        &self,
+        key: &str,
+        fingerprint: &str,
+        ttl: Duration,
+    ) -> Result<IdempotencyClaim, Error>;
+
+    /// Stores the response of the request that claimed `key`
+    async fn complete(
+        &self,
+        key: &str,
+        fingerprint: &str,
+        response: StoredResponse,
+        ttl: Duration,
+    ) -> Result<(), Error>;
+
+    /// Frees `key` so the request can be retried
+    async fn release(&self, key: &str) -> Result<(), Error>;
+}
+
+enum MemoryEntry {
+    InProgress,
+    Completed {
+        fingerprint: String,
+        response: StoredResponse,
+    },
+}
+
+/// Keeps idempotency keys in memory. Keys are not shared between instances,
+/// so use a shared store when running several replicas
+#[derive(Clone, Default)]
+pub struct MemoryIdempotencyStore {
+    entries: Arc<Mutex<HashMap<String, (MemoryEntry, Instant)>>>,
+}
+
+impl MemoryIdempotencyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait::async_trait]
+impl IdempotencyStore for MemoryIdempotencyStore {
+    async fn claim(
+        &self,
+        key: &str,
+        _fingerprint: &str,
+        ttl: Duration,
+    ) -> Result<IdempotencyClaim, Error> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        entries.retain(|_, (_, expires)| *expires > now);
+
+        match entries.get(key) {
+            Some((MemoryEntry::InProgress, _)) => Ok(IdempotencyClaim::InProgress),
+            Some((
+                MemoryEntry::Completed {
+                    fingerprint,
+                    response,
+                },
+                _,
+            )) => Ok(IdempotencyClaim::Completed {
+                fingerprint: fingerprint.clone(),
+                response: response.clone(),
+            }),
+            None => {
+                entries.insert(key.to_string(), (MemoryEntry::InProgress, now + ttl));
+                Ok(IdempotencyClaim::Acquired)
+            }
+        }
+    }
+
+    async fn complete(
+        &self,
+        key: &str,
+        fingerprint: &str,
+        response: StoredResponse,
+        ttl: Duration,
+    ) -> Result<(), Error> {
+        let entry = MemoryEntry::Completed {
+            fingerprint: fingerprint.to_string(),
+            response,
+        };
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), (entry, Instant::now() + ttl));
+        Ok(())
+    }
+
+    async fn release(&self, key: &str) -> Result<(), Error> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+/// ## IdempotencyOptions
+/// Options of the `Idempotency-Key` middleware enabled with
+/// `with_idempotency`
+#[derive(Clone)]
+pub struct IdempotencyOptions {
+    /// Where keys and responses are kept
+    ///
+    /// @defaults to [MemoryIdempotencyStore]
+    pub store: Arc<dyn IdempotencyStore>,
+
+    /// Request header carrying the key
+    ///
+    /// @defaults to idempotency-key
+    pub header: String,
+
+    /// How long responses are replayed
+    ///
+    /// @defaults to 24 hours
+    pub ttl: Duration,
+
+    /// How long a key stays locked by a running request. Bounds the lock
+    /// when the instance holding it dies before releasing it
+    ///
+    /// @defaults to 5 minutes
+    pub lock_ttl: Duration,
+
+    /// Largest request body buffered to fingerprint a request, larger ones
+    /// are rejected with 413
+    ///
+    /// @defaults to 1 MiB
+    pub max_body_size: usize,
+
+    /// Methods the middleware applies to
+    ///
+    /// @defaults to POST, PATCH
+    pub methods: Vec<Method>,
+}
+
+impl Default for IdempotencyOptions {
+    fn default() -> Self {
+        IdempotencyOptions {
+            store: Arc::new(MemoryIdempotencyStore::new()),
+            header: "idempotency-key".to_string(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            lock_ttl: Duration::from_secs(5 * 60),
+            max_body_size: 1024 * 1024,
+            methods: vec![Method::POST, Method::PATCH],
+        }
+    }
+}
+
+impl IdempotencyOptions {
+    pub fn store(mut self, store: impl IdempotencyStore + 'static) -> Self {
+        self.store = Arc::new(store);
+        self
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn lock_ttl(mut self, lock_ttl: Duration) -> Self {
+        self.lock_ttl = lock_ttl;
+        self
+    }
+
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+}
+
+/// Replays the stored response of requests repeating an `Idempotency-Key`
+pub(crate) struct Idempotency<E> {
+    inner: E,
+    options: IdempotencyOptions,
+}
+
+impl<E> Idempotency<E> {
+    pub(crate) fn new(inner: E, options: IdempotencyOptions) -> Self {
+        Idempotency { inner, options }
+    }
+}
+
+/// Releases a claimed key unless the request completed, so a handler that
+/// panics or whose client disconnects does not lock the key
+struct ClaimGuard {
+    store: Arc<dyn IdempotencyStore>,
+    key: Option<String>,
+}
+
+impl ClaimGuard {
+    fn disarm(&mut self) {
+        self.key = None;
+    }
+}
+
+impl Drop for ClaimGuard {
+    fn drop(&mut self) {
+        if let (Some(key), Ok(runtime)) = (self.key.take(), tokio::runtime::Handle::try_current()) {
+            let store = self.store.clone();
+            runtime.spawn(async move {
+                if let Err(e) = store.release(&key).await {
+                    log::error!("[North] could not release idempotency key {key}: {e}");
+                }
+            });
+        }
+    }
+}
+
+/// Identifies a request by method, path and body, so a key reused for a
+/// different request is detected. FNV-1a, stable across processes and builds
+/// unlike the std hasher, so replicas sharing a store agree on it
+fn fingerprint(req: &Request, body: &[u8]) -> String {
+    let query = req.uri().query().unwrap_or_default();
+    let parts: [&[u8]; 4] = [
+        req.method().as_str().as_bytes(),
+        req.uri().path().as_bytes(),
+        query.as_bytes(),
+        body,
+    ];
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in part.iter().chain([0].iter()) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{hash:016x}")
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for Idempotency<E> {
+    type Output = Response;
+
+    async fn call(&self, mut req: Request) -> poem::Result<Self::Output> {
+        let key = req
+            .headers()
+            .get(self.options.header.as_str())
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let key = match key {
+            Some(key) if self.options.methods.contains(req.method()) => key,
+            _ => return self.inner.call(req).await.map(IntoResponse::into_response),
+        };
+
+        let body = req
+            .take_body()
+            .into_bytes_limit(self.options.max_body_size)
+            .await?;
+        let fingerprint = fingerprint(&req, &body);
+        req.set_body(body);
+
+        let store = &self.options.store;
+        match store
+            .claim(&key, &fingerprint, self.options.lock_ttl)
+            .await?
+        {
+            IdempotencyClaim::Acquired => {}
+            IdempotencyClaim::InProgress => {
+                return Err(Error::Conflict(format!(
+                    "a request with idempotency key {key} is in progress"
+                ))
+                .into())
+            }
+            IdempotencyClaim::Completed {
+                fingerprint: stored,
+                response,
+            } => {
+                if stored != fingerprint {
+                    return Err(Error::Conflict(format!(
+                        "idempotency key {key} was used for a different request"
+                    ))
+                    .into());
+                }
+                return Ok(response.into_response());
+            }
+        }
+
+        let mut guard = ClaimGuard {
+            store: store.clone(),
+            key: Some(key.clone()),
+        };
+        let response = match self.inner.call(req).await {
+            Ok(response) => response.into_response(),
+            Err(e) => e.into_response(),
+        };
+        // server errors are not replayed so the client can retry them
+        if response.status().is_server_error() {
+            guard.disarm();
+            store.release(&key).await?;
+            return Ok(response);
+        }
+
+        let (parts, body) = response.into_parts();
+        let body = body.into_bytes().await?;
+        guard.disarm();
+        let stored = StoredResponse {
+            status: parts.status.as_u16(),
+            headers: parts
+                .headers
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            body: body.to_vec(),
+        };
+        store
+            .complete(&key, &fingerprint, stored, self.options.ttl)
+            .await?;
+
+        Ok(Response::from_parts(parts, body.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poem::test::TestClient;
+    use poem::{handler, EndpointExt};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn app(calls: Arc<AtomicUsize>) -> impl Endpoint {
+        #[handler]
+        async fn create(calls: poem::web::Data<&Arc<AtomicUsize>>, body: String) -> String {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let call = calls.fetch_add(1, Ordering::SeqCst) + 1;
+            format!("order {call}: {body}")
+        }
+
+        Idempotency::new(create.data(calls), IdempotencyOptions::default())
+    }
+
+    #[tokio::test]
+    async fn it_replays_the_first_response() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let client = TestClient::new(app(calls.clone()));
+
+        for _ in 0..2 {
+            let response = client
+                .post("/")
+                .header("idempotency-key", "k1")
+                .body("pizza")
+                .send()
+                .await;
+            response.assert_status_is_ok();
+            response.assert_text("order 1: pizza").await;
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn it_rejects_a_reused_key_with_another_body() {
+        let client = TestClient::new(app(Arc::new(AtomicUsize::new(0))));
+
+        client
+            .post("/")
+            .header("idempotency-key", "k1")
+            .body("pizza")
+            .send()
+            .await
+            .assert_status_is_ok();
+        client
+            .post("/")
+            .header("idempotency-key", "k1")
+            .body("pasta")
+            .send()
+            .await
+            .assert_status(StatusCode::CONFLICT);
+    }
+
+    #[tokio::test]
+    async fn it_locks_concurrent_duplicates() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let client = TestClient::new(app(calls.clone()));
+
+        let send = || {
+            client
+                .post("/")
+                .header("idempotency-key", "k1")
+                .body("pizza")
+                .send()
+        };
+        let (first, second) = tokio::join!(send(), send());
+
+        let mut statuses = [first.0.status(), second.0.status()];
+        statuses.sort();
+        assert_eq!(statuses, [StatusCode::OK, StatusCode::CONFLICT]);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn it_rejects_bodies_over_the_limit() {
+        #[handler]
+        async fn create(body: String) -> String {
+            body
+        }
+        let options = IdempotencyOptions::default().max_body_size(4);
+        let client = TestClient::new(Idempotency::new(create, options));
+
+        client
+            .post("/")
+            .header("idempotency-key", "k1")
+            .body("pizza")
+            .send()
+            .await
+            .assert_status(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn it_releases_the_key_of_an_abandoned_request() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let endpoint = app(calls.clone());
+        let request = || {
+            Request::builder()
+                .method(Method::POST)
+                .header("idempotency-key", "k1")
+                .body("pizza")
+        };
+
+        // the client goes away while the handler runs
+        let abandoned = tokio::time::timeout(Duration::from_millis(5), endpoint.call(request()));
+        assert!(abandoned.await.is_err());
+        tokio::task::yield_now().await;
+
+        let response = endpoint.call(request()).await.unwrap().into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn it_fingerprints_requests_stably() {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri_str("/orders?tip=1")
+            .finish();
+
+        assert_eq!(fingerprint(&request, b"pizza"), "a38770b5f668cbf6");
+        assert_ne!(
+            fingerprint(&request, b"pizza"),
+            fingerprint(&request, b"pasta")
+        );
+    }
+}
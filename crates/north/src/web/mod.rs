@@ -1 +1,17 @@
+#[cfg(feature = "api-poem")]
+pub mod access_log;
 pub mod addrs;
+#[cfg(feature = "api-poem")]
+pub mod assets;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "api-poem")]
+pub mod idempotency;
+#[cfg(feature = "api-poem")]
+pub mod session;
+#[cfg(feature = "api-poem")]
+pub mod sse;
+#[cfg(feature = "api-poem")]
+pub mod websocket;
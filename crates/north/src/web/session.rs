@@ -0,0 +1,445 @@
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use poem::endpoint::BoxEndpoint;
+#[cfg(feature = "session-redis")]
+use poem::session::RedisStorage;
+use poem::session::{
+    CookieConfig, CookieSession, MemoryStorage, ServerSession, Session, SessionStatus,
+    SessionStorage,
+};
+use poem::web::cookie::{CookieKey, SameSite};
+use poem::{Endpoint, EndpointExt, FromRequest, IntoResponse, Request, RequestBody, Response};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// Where session entries are kept
+#[derive(Clone, Debug)]
+pub enum SessionStore {
+    /// Entries are kept in the cookie itself. The cookie is signed or
+    /// encrypted according to [CookieSecurity]
+    Cookie,
+
+    /// Entries are kept in memory, the cookie only carries the session ID.
+    /// Sessions are lost on restart and are not shared between instances
+    Memory,
+
+    /// Entries are kept in Redis, e.g `redis://127.0.0.1:6379`
+    #[cfg(feature = "session-redis")]
+    Redis { url: String },
+}
+
+/// How the session cookie is protected
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CookieSecurity {
+    /// The cookie can be read by the client but not tampered with
+    Signed,
+
+    /// The cookie is encrypted and authenticated
+    #[default]
+    Private,
+}
+
+/// ## SessionConfig
+/// Options of the session middleware enabled with `with_sessions`
+#[derive(Clone, Debug)]
+pub struct SessionConfig {
+    /// Session storage
+    pub store: SessionStore,
+
+    /// Name of the session cookie
+    ///
+    /// @defaults to north-session
+    pub cookie_name: String,
+
+    /// Secret of at least 64 bytes used to sign or encrypt cookies. A random
+    /// key is generated when missing, which invalidates sessions on restart
+    ///
+    /// @defaults to [None]
+    pub key: Option<Vec<u8>>,
+
+    /// Cookie protection
+    ///
+    /// @defaults to Private
+    pub security: CookieSecurity,
+
+    /// Lifetime of a session. `None` creates browser sessions kept until the
+    /// browser is closed
+    ///
+    /// @defaults to 24 hours
+    pub ttl: Option<Duration>,
+
+    /// Extend the session lifetime on every request instead of expiring it
+    /// `ttl` after it was last changed
+    ///
+    /// @defaults to True
+    pub rolling: bool,
+
+    /// Only send the cookie over HTTPS
+    ///
+    /// @defaults to True
+    pub secure: bool,
+
+    /// SameSite attribute of the cookie
+    ///
+    /// @defaults to Lax
+    pub same_site: SameSite,
+}
+
+impl SessionConfig {
+    pub fn new(store: SessionStore) -> Self {
+        SessionConfig {
+            store,
+            cookie_name: "north-session".to_string(),
+            key: None,
+            security: CookieSecurity::default(),
+            ttl: Some(Duration::from_secs(24 * 60 * 60)),
+            rolling: true,
+            secure: true,
+            same_site: SameSite::Lax,
+        }
+    }
+
+    /// Sessions kept in a cookie protected with `key`
+    pub fn cookie(key: &[u8]) -> Self {
+        Self::new(SessionStore::Cookie).key(key)
+    }
+
+    /// Sessions kept in memory
+    pub fn memory() -> Self {
+        Self::new(SessionStore::Memory)
+    }
+
+    /// Sessions kept in Redis
+    #[cfg(feature = "session-redis")]
+    pub fn redis(url: &str) -> Self {
+        Self::new(SessionStore::Redis {
+            url: url.to_string(),
+        })
+    }
+
+    pub fn key(mut self, key: &[u8]) -> Self {
+        self.key = Some(key.to_vec());
+        self
+    }
+
+    pub fn ttl(mut self, ttl: Option<Duration>) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    pub fn rolling(mut self, rolling: bool) -> Self {
+        self.rolling = rolling;
+        self
+    }
+
+    fn cookie_key(&self) -> CookieKey {
+        match &self.key {
+            Some(key) => CookieKey::derive_from(key),
+            None => {
+                log::warn!("no session key configured, sessions will not survive a restart");
+                CookieKey::generate()
+            }
+        }
+    }
+
+    fn cookie_config(&self, key: &CookieKey, security: CookieSecurity) -> CookieConfig {
+        let config = match security {
+            CookieSecurity::Signed => CookieConfig::signed(key.clone()),
+            CookieSecurity::Private => CookieConfig::private(key.clone()),
+        };
+        config
+            .name(self.cookie_name.as_str())
+            .path("/")
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(self.same_site)
+            .max_age(self.ttl)
+    }
+}
+
+/// Wraps the app with the session middleware. Redis stores connect here,
+/// so a misconfigured Redis fails startup instead of the first request.
+pub(crate) async fn session_endpoint(
+    end: BoxEndpoint<'static>,
+    config: &SessionConfig,
+) -> Result<BoxEndpoint<'static>, Error> {
+    let key = config.cookie_key();
+    let rolling = config.rolling && config.ttl.is_some();
+
+    // server side stores only keep the session ID in the cookie, signing it
+    // is enough to reject forged IDs
+    let security = match &config.store {
+        SessionStore::Cookie => config.security,
+        _ => CookieSecurity::Signed,
+    };
+    let end = RollingSession {
+        inner: end,
+        cookie: config.cookie_config(&key, security),
+        carries_entries: matches!(config.store, SessionStore::Cookie),
+        rolling,
+    };
+    let cookie = config.cookie_config(&key, security);
+
+    Ok(match &config.store {
+        SessionStore::Cookie => end.with(CookieSession::new(cookie)).boxed(),
+        SessionStore::Memory => end
+            .with(ServerSession::new(
+                cookie,
+                RollingStorage::new(MemoryStorage::new(), rolling, config.ttl),
+            ))
+            .boxed(),
+        #[cfg(feature = "session-redis")]
+        SessionStore::Redis { url } => {
+            let client = redis::Client::open(url.as_str())?;
+            let connection = redis::aio::ConnectionManager::new(client).await?;
+            end.with(ServerSession::new(
+                cookie,
+                RollingStorage::new(RedisStorage::new(connection), rolling, config.ttl),
+            ))
+            .boxed()
+        }
+    })
+}
+
+/// Re-sends the cookie of non-empty sessions left unchanged by the handler,
+/// so its max age is pushed back along with the storage expiry
+struct RollingSession<E> {
+    inner: E,
+    cookie: CookieConfig,
+    /// the cookie holds the entries rather than the session ID
+    carries_entries: bool,
+    rolling: bool,
+}
+
+#[poem::async_trait]
+impl<E: Endpoint> Endpoint for RollingSession<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        let session = req.extensions().get::<Session>().cloned();
+        let cookie_jar = req.cookie().clone();
+        let response = self.inner.call(req).await?.into_response();
+
+        if let Some(session) = session.filter(|_| self.rolling) {
+            if session.status() == SessionStatus::Unchanged && !session.is_empty() {
+                let value = match self.carries_entries {
+                    true => Some(serde_json::to_string(&session.entries()).unwrap_or_default()),
+                    false => self.cookie.get_cookie_value(&cookie_jar),
+                };
+                if let Some(value) = value {
+                    self.cookie.set_cookie_value(&cookie_jar, &value);
+                }
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// Server side storage renewing the expiry of a session whenever it is
+/// loaded, so sessions expire `ttl` after their last access
+struct RollingStorage<S> {
+    inner: S,
+    rolling: bool,
+    ttl: Option<Duration>,
+}
+
+impl<S> RollingStorage<S> {
+    fn new(inner: S, rolling: bool, ttl: Option<Duration>) -> Self {
+        RollingStorage {
+            inner,
+            rolling,
+            ttl,
+        }
+    }
+}
+
+#[poem::async_trait]
+impl<S: SessionStorage> SessionStorage for RollingStorage<S> {
+    async fn load_session(
+        &self,
+        session_id: &str,
+    ) -> poem::Result<Option<BTreeMap<String, Value>>> {
+        let entries = self.inner.load_session(session_id).await?;
+        if let Some(entries) = entries.as_ref().filter(|_| self.rolling) {
+            self.inner
+                .update_session(session_id, entries, self.ttl)
+                .await?;
+        }
+        Ok(entries)
+    }
+
+    async fn update_session(
+        &self,
+        session_id: &str,
+        entries: &BTreeMap<String, Value>,
+        expires: Option<Duration>,
+    ) -> poem::Result<()> {
+        self.inner
+            .update_session(session_id, entries, expires)
+            .await
+    }
+
+    async fn remove_session(&self, session_id: &str) -> poem::Result<()> {
+        self.inner.remove_session(session_id).await
+    }
+}
+
+/// A value stored in the session under a fixed key
+pub trait SessionValue: Serialize + DeserializeOwned + Send + Sync + 'static {
+    const KEY: &'static str;
+}
+
+/// ## TypedSession
+/// Typed access to a session value, extracted like any other request data
+///
+/// ```rust,ignore
+/// #[derive(Serialize, Deserialize)]
+/// struct CurrentUser { id: String }
+///
+/// impl SessionValue for CurrentUser {
+///     const KEY: &'static str = "user";
+/// }
+///
+/// #[oai(path = "/login", method = "post")]
+/// async fn login(&self, session: TypedSession<CurrentUser>) -> PlainText<String> {
+///     // a new session ID prevents session fixation
+///     session.renew();
+///     session.set(CurrentUser { id: "42".to_string() });
+///     PlainText("ok".to_string())
+/// }
+/// ```
+pub struct TypedSession<T> {
+    session: Session,
+    _value: PhantomData<T>,
+}
+
+impl<T: SessionValue> TypedSession<T> {
+    pub fn get(&self) -> Option<T> {
+        self.session.get::<T>(T::KEY)
+    }
+
+    pub fn set(&self, value: T) {
+        self.session.set(T::KEY, value);
+    }
+
+    pub fn remove(&self) {
+        self.session.remove(T::KEY);
+    }
+
+    /// Issues a new session ID and keeps the entries. Call it whenever the
+    /// privileges of the session change, e.g on login
+    pub fn renew(&self) {
+        self.session.renew();
+    }
+
+    /// Drops the session and its cookie
+    pub fn purge(&self) {
+        self.session.purge();
+    }
+
+    /// The untyped session
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+}
+
+#[poem::async_trait]
+impl<'a, T: SessionValue> FromRequest<'a> for TypedSession<T> {
+    async fn from_request(req: &'a Request, _body: &mut RequestBody) -> poem::Result<Self> {
+        let session = <&Session>::from_request_without_body(req).await?;
+        Ok(TypedSession {
+            session: session.clone(),
+            _value: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use poem::handler;
+    use poem::http::header;
+    use poem::test::TestClient;
+
+    #[derive(Serialize, Deserialize)]
+    struct Counter(u32);
+
+    impl SessionValue for Counter {
+        const KEY: &'static str = "counter";
+    }
+
+    #[handler]
+    fn count(session: TypedSession<Counter>) -> String {
+        let next = session.get().map(|c| c.0).unwrap_or_default() + 1;
+        session.set(Counter(next));
+        next.to_string()
+    }
+
+    #[handler]
+    fn keys(session: &Session) -> String {
+        session
+            .entries()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    fn session_cookie(response: &poem::test::TestResponse) -> Option<String> {
+        let cookie = response.0.headers().get(header::SET_COOKIE)?;
+        Some(
+            cookie
+                .to_str()
+                .unwrap()
+                .split(';')
+                .next()
+                .unwrap()
+                .to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn it_keeps_typed_values_between_requests() {
+        let mut config = SessionConfig::memory();
+        config.secure = false;
+        let app = session_endpoint(count.boxed(), &config).await.unwrap();
+        let client = TestClient::new(app);
+
+        let response = client.get("/").send().await;
+        let cookie = session_cookie(&response).unwrap();
+        response.assert_text("1").await;
+
+        let response = client.get("/").header(header::COOKIE, cookie).send().await;
+        response.assert_text("2").await;
+    }
+
+    #[tokio::test]
+    async fn it_rolls_sessions_without_touching_their_entries() {
+        for (mut config, rolling) in [
+            (SessionConfig::memory(), true),
+            (SessionConfig::cookie(&[7; 64]), true),
+            (SessionConfig::memory().rolling(false), false),
+        ] {
+            config.secure = false;
+            let app = poem::Route::new().at("/count", count).at("/keys", keys);
+            let app = session_endpoint(app.boxed(), &config).await.unwrap();
+            let client = TestClient::new(app);
+
+            let response = client.get("/count").send().await;
+            let cookie = session_cookie(&response).unwrap();
+
+            let response = client
+                .get("/keys")
+                .header(header::COOKIE, cookie)
+                .send()
+                .await;
+            assert_eq!(session_cookie(&response).is_some(), rolling);
+            response.assert_text("counter").await;
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use north_common::state::NorthStateData;
+use poem::web::sse::{Event, SSE};
+use poem::{IntoResponse, Response};
+use poem_openapi::payload::{EventStream, Payload};
+use poem_openapi::registry::{MetaResponses, MetaSchemaRef, Registry};
+use poem_openapi::types::{ToJSON, Type};
+use poem_openapi::ApiResponse;
+use tokio::sync::{broadcast, mpsc};
+
+/// # SseOptions
+///
+/// Options of a server-sent events channel
+#[derive(Clone, Debug)]
+pub struct SseOptions {
+    /// Number of past events kept to replay to clients reconnecting with a
+    /// `Last-Event-ID` header
+    ///
+    /// @defaults to 256
+    pub replay_buffer: usize,
+
+    /// Interval between heartbeat comments. `None` disables heartbeats
+    ///
+    /// @defaults to 15 seconds
+    pub heartbeat: Option<Duration>,
+
+    /// Number of events buffered per client. Slow clients skip events and
+    /// can catch up by reconnecting with `Last-Event-ID`
+    ///
+    /// @defaults to 128
+    pub client_capacity: usize,
+}
+
+impl Default for SseOptions {
+    fn default() -> Self {
+        SseOptions {
+            replay_buffer: 256,
+            heartbeat: Some(Duration::from_secs(15)),
+            client_capacity: 128,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct SseEnvelope<T> {
+    id: u64,
+    data: T,
+}
+
+struct SseBuffer<T> {
+    last_id: u64,
+    events: VecDeque<SseEnvelope<T>>,
+}
+
+struct SseChannelInner<T> {
+    options: SseOptions,
+    buffer: Mutex<SseBuffer<T>>,
+    sender: broadcast::Sender<SseEnvelope<T>>,
+}
+
+/// ## SseChannel
+/// Turns application events into server-sent event streams. Each event gets
+/// an increasing ID and the latest events are kept so reconnecting clients
+/// can resume from their `Last-Event-ID`.
+///
+/// Register the channel with `sse_channel` and return [SseStream] from an
+/// OpenApi controller:
+///
+/// ```rust,ignore
+/// #[oai(path = "/orders/events", method = "get")]
+/// async fn order_events(
+///     &self,
+///     channel: Data<&SseChannel<Order>>,
+///     #[oai(name = "Last-Event-ID")] last_event_id: Header<Option<String>>,
+/// ) -> SseStream<Order> {
+///     channel.subscribe(last_event_id.0.as_deref())
+/// }
+/// ```
+pub struct SseChannel<T> {
+    inner: Arc<SseChannelInner<T>>,
+}
+
+impl<T> Clone for SseChannel<T> {
+    fn clone(&self) -> Self {
+        SseChannel {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T: Clone + Send + Sync + 'static> NorthStateData for SseChannel<T> {}
+
+impl<T: Clone + Send + Sync + 'static> SseChannel<T> {
+    pub fn new(options: SseOptions) -> Self {
+        let (sender, _) = broadcast::channel(options.client_capacity.max(1));
+        SseChannel {
+            inner: Arc::new(SseChannelInner {
+                buffer: Mutex::new(SseBuffer {
+                    last_id: 0,
+                    events: VecDeque::with_capacity(options.replay_buffer),
+                }),
+                options,
+                sender,
+            }),
+        }
+    }
+
+    /// Creates a channel fed by a tokio broadcast receiver
+    pub fn from_broadcast(mut receiver: broadcast::Receiver<T>, options: SseOptions) -> Self {
+        let channel = Self::new(options);
+        let publisher = channel.clone();
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(data) => {
+                        publisher.publish(data);
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!(
+                            "sse channel lagged behind its source, {skipped} events skipped"
+                        );
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        channel
+    }
+
+    /// Creates a channel fed by a tokio mpsc receiver
+    pub fn from_mpsc(mut receiver: mpsc::Receiver<T>, options: SseOptions) -> Self {
+        let channel = Self::new(options);
+        let publisher = channel.clone();
+        tokio::spawn(async move {
+            while let Some(data) = receiver.recv().await {
+                publisher.publish(data);
+            }
+        });
+        channel
+    }
+
+    /// Publishes an event to every connected client and returns its ID
+    pub fn publish(&self, data: T) -> u64 {
+        let mut buffer = self.inner.buffer.lock().unwrap();
+        buffer.last_id += 1;
+
+        let envelope = SseEnvelope {
+            id: buffer.last_id,
+            data,
+        };
+        if self.inner.options.replay_buffer > 0 {
+            if buffer.events.len() == self.inner.options.replay_buffer {
+                buffer.events.pop_front();
+            }
+            buffer.events.push_back(envelope.clone());
+        }
+        let _ = self.inner.sender.send(envelope);
+
+        buffer.last_id
+    }
+
+    /// ID of the last published event
+    pub fn last_event_id(&self) -> u64 {
+        self.inner.buffer.lock().unwrap().last_id
+    }
+
+    /// Opens a stream for a client. Buffered events newer than
+    /// `last_event_id` are replayed before live events.
+    pub fn subscribe(&self, last_event_id: Option<&str>) -> SseStream<T> {
+        let after = last_event_id.and_then(|id| id.trim().parse::<u64>().ok());
+
+        // the buffer lock is held while subscribing so no event is missed or
+        // replayed twice
+        let (replay, receiver) = {
+            let buffer = self.inner.buffer.lock().unwrap();
+            let replay: Vec<SseEnvelope<T>> = match after {
+                Some(after) => buffer
+                    .events
+                    .iter()
+                    .filter(|event| event.id > after)
+                    .cloned()
+                    .collect(),
+                None => vec![],
+            };
+            (replay, self.inner.sender.subscribe())
+        };
+
+        let live = stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+
+        SseStream {
+            events: stream::iter(replay)
+                .chain(live)
+                .map(|event| (event.id, event.data))
+                .boxed(),
+            heartbeat: self.inner.options.heartbeat,
+        }
+    }
+}
+
+/// ## SseStream
+/// A `text/event-stream` response. It can be returned from OpenApi
+/// operations and is documented as an event stream of `T`.
+pub struct SseStream<T> {
+    events: BoxStream<'static, (u64, T)>,
+    heartbeat: Option<Duration>,
+}
+
+impl<T: ToJSON + Send + 'static> IntoResponse for SseStream<T> {
+    fn into_response(self) -> Response {
+        let events = self.events.map(|(id, data)| {
+            Event::message(serde_json::to_string(&data.to_json()).unwrap_or_default())
+                .id(id.to_string())
+        });
+
+        let mut sse = SSE::new(events);
+        if let Some(heartbeat) = self.heartbeat {
+            sse = sse.keep_alive(heartbeat);
+        }
+        sse.into_response()
+    }
+}
+
+impl<T: Type + ToJSON + Send + 'static> Payload for SseStream<T> {
+    const CONTENT_TYPE: &'static str = "text/event-stream";
+
+    fn schema_ref() -> MetaSchemaRef {
+        <EventStream<BoxStream<'static, T>> as Payload>::schema_ref()
+    }
+
+    fn register(registry: &mut Registry) {
+        T::register(registry);
+    }
+}
+
+impl<T: Type + ToJSON + Send + 'static> ApiResponse for SseStream<T> {
+    fn meta() -> MetaResponses {
+        <EventStream<BoxStream<'static, T>> as ApiResponse>::meta()
+    }
+
+    fn register(registry: &mut Registry) {
+        T::register(registry);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> SseOptions {
+        SseOptions {
+            replay_buffer: 2,
+            heartbeat: None,
+            client_capacity: 8,
+        }
+    }
+
+    #[tokio::test]
+    async fn it_replays_events_after_last_event_id() {
+        let channel = SseChannel::new(options());
+        channel.publish("first".to_string());
+        channel.publish("second".to_string());
+        channel.publish("third".to_string());
+
+        let mut stream = channel.subscribe(Some("1")).events;
+        assert_eq!(stream.next().await.unwrap(), (2, "second".to_string()));
+        assert_eq!(stream.next().await.unwrap(), (3, "third".to_string()));
+
+        channel.publish("fourth".to_string());
+        assert_eq!(stream.next().await.unwrap(), (4, "fourth".to_string()));
+    }
+
+    #[tokio::test]
+    async fn it_forwards_events_from_mpsc() {
+        let (sender, receiver) = mpsc::channel(8);
+        let channel = SseChannel::from_mpsc(receiver, options());
+        let mut stream = channel.subscribe(None).events;
+
+        sender.send(10).await.unwrap();
+        assert_eq!(stream.next().await.unwrap(), (1, 10));
+    }
+}
@@ -0,0 +1,439 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::{SinkExt, StreamExt};
+use north_common::state::{NorthStateData, NorthStateMap};
+use poem::error::WebSocketError;
+use poem::http::{header, Method, StatusCode};
+use poem::web::websocket::{CloseCode, Message};
+use poem::{Endpoint, Request, Response, Upgraded};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite;
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::{Role, WebSocketConfig};
+use tokio_tungstenite::WebSocketStream;
+
+use crate::shutdown::ShutdownHandle;
+
+pub(crate) type WebSocketHandler =
+    Arc<dyn Fn(WebSocketSession) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// # WebSocketOptions
+///
+/// Options shared by every websocket route of a service
+#[derive(Clone, Debug)]
+pub struct WebSocketOptions {
+    /// Interval between keepalive pings. `None` disables keepalive
+    ///
+    /// @defaults to 30 seconds
+    pub ping_interval: Option<Duration>,
+
+    /// Time allowed for the client to answer a ping before the connection
+    /// is closed
+    ///
+    /// @defaults to 10 seconds
+    pub pong_timeout: Duration,
+
+    /// Max size in bytes of a text or binary message. Larger messages close
+    /// the connection with [CloseCode::Size]
+    ///
+    /// @defaults to 64 KiB
+    pub max_message_size: usize,
+
+    /// Max size in bytes of a single frame of a message. Larger frames close
+    /// the connection with [CloseCode::Size]
+    ///
+    /// @defaults to 64 KiB
+    pub max_frame_size: usize,
+
+    /// Number of messages buffered per session and per hub topic
+    ///
+    /// @defaults to 128
+    pub channel_capacity: usize,
+}
+
+impl Default for WebSocketOptions {
+    fn default() -> Self {
+        WebSocketOptions {
+            ping_interval: Some(Duration::from_secs(30)),
+            pong_timeout: Duration::from_secs(10),
+            max_message_size: 64 * 1024,
+            max_frame_size: 64 * 1024,
+            channel_capacity: 128,
+        }
+    }
+}
+
+/// ## BroadcastHub
+/// Topic based publish/subscribe hub shared by all websocket sessions of a
+/// service. It is also registered as state data, so controllers can publish
+/// with `Data<&BroadcastHub>`.
+#[derive(Clone)]
+pub struct BroadcastHub {
+    capacity: usize,
+    topics: Arc<RwLock<HashMap<String, broadcast::Sender<Message>>>>,
+}
+
+impl NorthStateData for BroadcastHub {}
+
+impl Default for BroadcastHub {
+    fn default() -> Self {
+        BroadcastHub::new(WebSocketOptions::default().channel_capacity)
+    }
+}
+
+impl BroadcastHub {
+    pub fn new(capacity: usize) -> Self {
+        BroadcastHub {
+            capacity: capacity.max(1),
+            topics: Default::default(),
+        }
+    }
+
+    /// Publishes a message to every subscriber of a topic and returns the
+    /// number of subscribers it was sent to.
+    pub fn publish(&self, topic: &str, message: impl Into<Message>) -> usize {
+        let topics = self.topics.read().unwrap();
+        match topics.get(topic) {
+            Some(sender) => sender.send(message.into()).unwrap_or(0),
+            None => 0,
+        }
+    }
+
+    /// Subscribes to a topic, creating it when needed
+    pub fn subscribe(&self, topic: &str) -> broadcast::Receiver<Message> {
+        let mut topics = self.topics.write().unwrap();
+        topics.retain(|_, sender| sender.receiver_count() > 0);
+        topics
+            .entry(topic.to_string())
+            .or_insert_with(|| broadcast::channel(self.capacity).0)
+            .subscribe()
+    }
+
+    /// Number of active subscribers of a topic
+    pub fn subscriber_count(&self, topic: &str) -> usize {
+        let topics = self.topics.read().unwrap();
+        topics.get(topic).map(|s| s.receiver_count()).unwrap_or(0)
+    }
+
+    /// Topics with at least one subscriber
+    pub fn topics(&self) -> Vec<String> {
+        let topics = self.topics.read().unwrap();
+        topics
+            .iter()
+            .filter(|(_, sender)| sender.receiver_count() > 0)
+            .map(|(topic, _)| topic.clone())
+            .collect()
+    }
+}
+
+/// Cloneable handle used to send messages to a websocket client
+#[derive(Clone)]
+pub struct WebSocketSender {
+    sender: mpsc::Sender<Message>,
+}
+
+impl WebSocketSender {
+    /// Queues a message for the client. Returns false when the connection
+    /// is closed.
+    pub async fn send(&self, message: impl Into<Message>) -> bool {
+        self.sender.send(message.into()).await.is_ok()
+    }
+}
+
+/// ## WebSocketSession
+/// A connected websocket client handed to websocket handlers. Keepalive,
+/// message size limits and closing on shutdown are handled by North; the
+/// handler only sees text and binary messages.
+pub struct WebSocketSession {
+    incoming: mpsc::Receiver<Message>,
+    sender: WebSocketSender,
+    hub: BroadcastHub,
+    state: NorthStateMap,
+    remote_addr: String,
+    subscriptions: Vec<JoinHandle<()>>,
+}
+
+impl WebSocketSession {
+    /// Waits for the next text or binary message. Returns `None` once the
+    /// connection is closed.
+    pub async fn recv(&mut self) -> Option<Message> {
+        self.incoming.recv().await
+    }
+
+    /// Sends a message to the client
+    pub async fn send(&self, message: impl Into<Message>) -> bool {
+        self.sender.send(message).await
+    }
+
+    /// Returns a cloneable sender for this client
+    pub fn sender(&self) -> WebSocketSender {
+        self.sender.clone()
+    }
+
+    /// The hub shared by all sessions of the service
+    pub fn hub(&self) -> &BroadcastHub {
+        &self.hub
+    }
+
+    /// Access state data registered with `with_data`
+    pub fn data<T: 'static>(&self) -> Option<&T> {
+        self.state.get::<T>()
+    }
+
+    pub fn remote_addr(&self) -> &str {
+        self.remote_addr.as_str()
+    }
+
+    /// Forwards every message published to `topic` to this client until the
+    /// session ends
+    pub fn join(&mut self, topic: &str) {
+        let mut receiver = self.hub.subscribe(topic);
+        let sender = self.sender.clone();
+        let topic = topic.to_string();
+
+        self.subscriptions.push(tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(message) => {
+                        if !sender.send(message).await {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("websocket client lagged behind topic {topic}, {skipped} messages skipped");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }));
+    }
+}
+
+impl Drop for WebSocketSession {
+    fn drop(&mut self) {
+        for subscription in self.subscriptions.drain(..) {
+            subscription.abort();
+        }
+    }
+}
+
+pub(crate) fn into_handler<F, Fut>(handler: F) -> WebSocketHandler
+where
+    F: Fn(WebSocketSession) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    Arc::new(move |session| Box::pin(handler(session)))
+}
+
+/// Poem endpoint upgrading requests and driving websocket sessions
+pub(crate) struct WebSocketEndpoint {
+    pub(crate) handler: WebSocketHandler,
+    pub(crate) hub: BroadcastHub,
+    pub(crate) state: NorthStateMap,
+    pub(crate) options: WebSocketOptions,
+    pub(crate) shutdown: ShutdownHandle,
+}
+
+#[poem::async_trait]
+impl Endpoint for WebSocketEndpoint {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        // the upgrade is done here rather than with poem's `WebSocket`
+        // extractor, which doesn't let the size limits be configured
+        let key = handshake_key(&req).ok_or(WebSocketError::InvalidProtocol)?;
+        let on_upgrade = req.take_upgrade()?;
+
+        let handler = self.handler.clone();
+        let hub = self.hub.clone();
+        let state = self.state.clone();
+        let options = self.options.clone();
+        let shutdown = self.shutdown.clone();
+        let remote_addr = req.remote_addr().to_string();
+
+        tokio::spawn(async move {
+            let upgraded = match on_upgrade.await {
+                Ok(upgraded) => upgraded,
+                Err(_) => return,
+            };
+            let config = WebSocketConfig {
+                max_message_size: Some(options.max_message_size),
+                max_frame_size: Some(options.max_frame_size),
+                ..Default::default()
+            };
+            let socket =
+                WebSocketStream::from_raw_socket(upgraded, Role::Server, Some(config)).await;
+
+            let (sender, outgoing) = mpsc::channel(options.channel_capacity);
+            let (incoming_sender, incoming) = mpsc::channel(options.channel_capacity);
+
+            let session = WebSocketSession {
+                incoming,
+                sender: WebSocketSender { sender },
+                hub,
+                state,
+                remote_addr,
+                subscriptions: vec![],
+            };
+
+            tokio::spawn(handler(session));
+            drive(socket, incoming_sender, outgoing, options, shutdown).await;
+        });
+
+        Ok(Response::builder()
+            .status(StatusCode::SWITCHING_PROTOCOLS)
+            .header(header::CONNECTION, "upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header(
+                header::SEC_WEBSOCKET_ACCEPT,
+                derive_accept_key(key.as_bytes()),
+            )
+            .finish())
+    }
+}
+
+/// Returns the `Sec-WebSocket-Key` of a valid websocket upgrade request
+fn handshake_key(req: &Request) -> Option<String> {
+    let headers = req.headers();
+    let header_is = |name, value: &str| {
+        headers
+            .get(name)
+            .and_then(|header| header.to_str().ok())
+            .map(|header| header.eq_ignore_ascii_case(value))
+            .unwrap_or(false)
+    };
+    let connection_upgrade = headers
+        .get(header::CONNECTION)
+        .and_then(|header| header.to_str().ok())
+        .map(|header| {
+            header
+                .split(',')
+                .any(|token| token.trim().eq_ignore_ascii_case("upgrade"))
+        })
+        .unwrap_or(false);
+
+    if req.method() != Method::GET
+        || !connection_upgrade
+        || !header_is(header::UPGRADE, "websocket")
+        || !header_is(header::SEC_WEBSOCKET_VERSION, "13")
+    {
+        return None;
+    }
+    headers
+        .get(header::SEC_WEBSOCKET_KEY)
+        .and_then(|key| key.to_str().ok())
+        .map(str::to_string)
+}
+
+/// Pumps messages between the socket and the session until either side
+/// closes, the client stops answering pings or the service shuts down.
+async fn drive(
+    socket: WebSocketStream<Upgraded>,
+    incoming: mpsc::Sender<Message>,
+    mut outgoing: mpsc::Receiver<Message>,
+    options: WebSocketOptions,
+    shutdown: ShutdownHandle,
+) {
+    let (mut sink, mut stream) = socket.split();
+
+    let keepalive = options.ping_interval.is_some();
+    let ping_interval = options.ping_interval.unwrap_or(Duration::from_secs(30));
+    let mut ping = tokio::time::interval_at(Instant::now() + ping_interval, ping_interval);
+    let mut last_pong = Instant::now();
+
+    let close = loop {
+        tokio::select! {
+            message = stream.next() => match message.map(|message| message.map(Message::from)) {
+                Some(Ok(Message::Pong(_))) => last_pong = Instant::now(),
+                Some(Ok(Message::Ping(_))) => {}
+                Some(Ok(Message::Close(_))) | None => break None,
+                Some(Err(tungstenite::Error::Capacity(_))) => {
+                    break Some(Message::close_with(CloseCode::Size, "message too big"));
+                }
+                Some(Err(_)) => break None,
+                Some(Ok(message)) => {
+                    if incoming.send(message).await.is_err() {
+                        break Some(Message::close());
+                    }
+                }
+            },
+            message = outgoing.recv() => match message {
+                Some(message) => {
+                    if sink.send(message.into()).await.is_err() {
+                        break None;
+                    }
+                }
+                None => break Some(Message::close()),
+            },
+            _ = ping.tick(), if keepalive => {
+                if last_pong.elapsed() > ping_interval + options.pong_timeout {
+                    break Some(Message::close_with(CloseCode::Away, "ping timeout"));
+                }
+                if sink.send(Message::ping(Vec::new()).into()).await.is_err() {
+                    break None;
+                }
+            },
+            _ = shutdown.wait() => {
+                break Some(Message::close_with(CloseCode::Away, "server shutting down"));
+            }
+        }
+    };
+
+    if let Some(message) = close {
+        let _ = sink.send(message.into()).await;
+    }
+    let _ = sink.close().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn it_publishes_to_topic_subscribers() {
+        let hub = BroadcastHub::new(8);
+        let mut orders = hub.subscribe("orders");
+        let _payments = hub.subscribe("payments");
+
+        assert_eq!(hub.publish("orders", Message::text("created")), 1);
+        assert_eq!(hub.publish("unknown", Message::text("lost")), 0);
+        assert_eq!(orders.recv().await.unwrap(), Message::text("created"));
+    }
+
+    #[test]
+    fn it_forgets_topics_without_subscribers() {
+        let hub = BroadcastHub::new(8);
+        drop(hub.subscribe("orders"));
+        let _payments = hub.subscribe("payments");
+
+        assert_eq!(hub.topics(), vec!["payments".to_string()]);
+        assert_eq!(hub.subscriber_count("orders"), 0);
+    }
+
+    #[test]
+    fn it_only_upgrades_websocket_requests() {
+        let request = Request::builder()
+            .header(header::CONNECTION, "keep-alive, Upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ==")
+            .finish();
+        assert_eq!(
+            handshake_key(&request).as_deref(),
+            Some("dGhlIHNhbXBsZSBub25jZQ==")
+        );
+
+        let request = Request::builder()
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_VERSION, "13")
+            .header(header::SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ==")
+            .finish();
+        assert_eq!(handshake_key(&request), None);
+    }
+}
@@ -0,0 +1,481 @@
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use north_common::state::NorthStateMap;
+use tokio::task::{AbortHandle, JoinHandle};
+
+use crate::error::Error;
+use crate::health::HealthIndicators;
+use crate::shutdown::ShutdownHandle;
+
+/// # WorkerOptions
+///
+/// Supervision of the workers and scheduled jobs of a service
+#[derive(Clone, Debug)]
+pub struct WorkerOptions {
+    /// Restart workers that return an error
+    ///
+    /// @defaults to True
+    pub restart_on_failure: bool,
+
+    /// Delay before the first restart, doubled on every failure in a row
+    ///
+    /// @defaults to 1 second
+    pub initial_backoff: Duration,
+
+    /// Upper bound of the restart delay. A worker that ran longer than this
+    /// before failing starts again from `initial_backoff`
+    ///
+    /// @defaults to 60 seconds
+    pub max_backoff: Duration,
+
+    /// Time given to workers and running jobs to return once shutdown starts,
+    /// after which they are aborted
+    ///
+    /// @defaults to 10 seconds
+    pub shutdown_grace: Duration,
+
+    /// Report workers and jobs as readiness components, so a failing one
+    /// takes the service out of the load balancer
+    ///
+    /// @defaults to False
+    pub report_readiness: bool,
+}
+
+impl Default for WorkerOptions {
+    fn default() -> Self {
+        WorkerOptions {
+            restart_on_failure: true,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            shutdown_grace: Duration::from_secs(10),
+            report_readiness: false,
+        }
+    }
+}
+
+impl WorkerOptions {
+    /// Restart delay after `failures` failures in a row
+    fn backoff(&self, failures: u32) -> Duration {
+        self.initial_backoff
+            .checked_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+/// ## WorkerContext
+/// Handed to workers and jobs on every run
+#[derive(Clone)]
+pub struct WorkerContext {
+    pub name: String,
+
+    /// state data registered on the service
+    pub state: NorthStateMap,
+
+    shutdown: ShutdownHandle,
+}
+
+impl WorkerContext {
+    /// returns true once shutdown has started
+    pub fn is_cancelled(&self) -> bool {
+        self.shutdown.is_triggered()
+    }
+
+    /// resolves when shutdown starts, workers should return soon after
+    pub async fn cancelled(&self) {
+        self.shutdown.wait().await
+    }
+}
+
+type WorkerFn = Arc<dyn Fn(WorkerContext) -> BoxFuture<'static, Result<(), Error>> + Send + Sync>;
+
+/// A long running task, such as a queue consumer
+#[derive(Clone)]
+pub(crate) struct Worker {
+    name: String,
+    run: WorkerFn,
+}
+
+/// A task run on a cron schedule
+#[derive(Clone)]
+pub(crate) struct ScheduledJob {
+    name: String,
+    cron: String,
+    run: WorkerFn,
+}
+
+fn worker_fn<F, Fut>(factory: F) -> WorkerFn
+where
+    F: Fn(WorkerContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<(), Error>> + Send + 'static,
+{
+    Arc::new(move |ctx| factory(ctx).boxed())
+}
+
+impl Worker {
+    pub(crate) fn new<F, Fut>(name: &str, factory: F) -> Self
+    where
+        F: Fn(WorkerContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        Worker {
+            name: name.to_string(),
+            run: worker_fn(factory),
+        }
+    }
+
+    fn component(&self) -> String {
+        format!("worker:{}", self.name)
+    }
+
+    /// Runs the worker once in its own task, so a panic is a failure like
+    /// any other instead of killing the supervisor
+    async fn run_once(&self, ctx: WorkerContext) -> Result<(), Error> {
+        let task = tokio::spawn((self.run)(ctx));
+        let _abort = AbortOnDrop(task.abort_handle());
+        match task.await {
+            Ok(result) => result,
+            Err(e) => Err(Error::InternalServerError(format!("worker panicked: {e}"))),
+        }
+    }
+}
+
+/// Aborts a worker run when its supervisor is aborted at the end of the
+/// shutdown grace period
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Health component of a worker or job, only reported when
+/// [WorkerOptions::report_readiness] is set
+struct WorkerHealth {
+    health: Option<HealthIndicators>,
+    component: String,
+}
+
+impl WorkerHealth {
+    fn new(health: &HealthIndicators, component: String, options: &WorkerOptions) -> Self {
+        WorkerHealth {
+            health: options.report_readiness.then(|| health.clone()),
+            component,
+        }
+    }
+
+    fn up(&self) {
+        if let Some(health) = self.health.as_ref() {
+            health.up(&self.component);
+        }
+    }
+
+    fn down(&self, details: String) {
+        if let Some(health) = self.health.as_ref() {
+            health.down(&self.component, details);
+        }
+    }
+
+    fn remove(&self) {
+        if let Some(health) = self.health.as_ref() {
+            health.remove(&self.component);
+        }
+    }
+}
+
+impl ScheduledJob {
+    pub(crate) fn new<F, Fut>(name: &str, cron: &str, job: F) -> Self
+    where
+        F: Fn(WorkerContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        ScheduledJob {
+            name: name.to_string(),
+            cron: cron.to_string(),
+            run: worker_fn(job),
+        }
+    }
+
+    fn component(&self) -> String {
+        format!("job:{}", self.name)
+    }
+
+    fn schedule(&self) -> Result<cron::Schedule, Error> {
+        parse_cron(&self.cron).map_err(|e| {
+            Error::BadRequest(format!(
+                "invalid schedule `{}` of job {}: {e}",
+                self.cron, self.name
+            ))
+        })
+    }
+}
+
+/// Parses a cron expression. Five field expressions are accepted and run on
+/// the first second of the minute
+fn parse_cron(expression: &str) -> Result<cron::Schedule, cron::error::Error> {
+    let expression = expression.trim();
+    if expression.split_whitespace().count() == 5 {
+        cron::Schedule::from_str(&format!("0 {expression}"))
+    } else {
+        cron::Schedule::from_str(expression)
+    }
+}
+
+/// Workers and jobs registered on a service
+#[derive(Clone, Default)]
+pub(crate) struct Workers {
+    pub(crate) workers: Vec<Worker>,
+    pub(crate) jobs: Vec<ScheduledJob>,
+    pub(crate) options: WorkerOptions,
+}
+
+impl Workers {
+    /// Spawns every worker and job. Invalid cron expressions fail here so a
+    /// typo stops startup instead of silently never running
+    pub(crate) fn start(
+        &self,
+        state: &NorthStateMap,
+        health: &HealthIndicators,
+        shutdown: &ShutdownHandle,
+    ) -> Result<RunningWorkers, Error> {
+        let schedules = self
+            .jobs
+            .iter()
+            .map(|job| job.schedule())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let context = |name: &str| WorkerContext {
+            name: name.to_string(),
+            state: state.clone(),
+            shutdown: shutdown.clone(),
+        };
+
+        let mut tasks = vec![];
+        for worker in self.workers.iter() {
+            tasks.push(tokio::spawn(supervise(
+                worker.clone(),
+                context(&worker.name),
+                WorkerHealth::new(health, worker.component(), &self.options),
+                self.options.clone(),
+            )));
+        }
+        for (job, schedule) in self.jobs.iter().zip(schedules) {
+            tasks.push(tokio::spawn(run_schedule(
+                job.clone(),
+                schedule,
+                context(&job.name),
+                WorkerHealth::new(health, job.component(), &self.options),
+            )));
+        }
+
+        Ok(RunningWorkers {
+            tasks,
+            grace: self.options.shutdown_grace,
+        })
+    }
+}
+
+/// Tasks spawned by [Workers::start]
+pub(crate) struct RunningWorkers {
+    tasks: Vec<JoinHandle<()>>,
+    grace: Duration,
+}
+
+impl RunningWorkers {
+    /// Waits for the tasks to return after shutdown started, aborting the
+    /// ones still running once the grace period is over
+    pub(crate) async fn stop(self) {
+        let deadline = tokio::time::Instant::now() + self.grace;
+        for mut task in self.tasks {
+            if tokio::time::timeout_at(deadline, &mut task).await.is_err() {
+                task.abort();
+            }
+        }
+    }
+}
+
+/// Runs a worker until shutdown, restarting it with backoff when it fails
+async fn supervise(
+    worker: Worker,
+    ctx: WorkerContext,
+    health: WorkerHealth,
+    options: WorkerOptions,
+) {
+    let mut failures = 0;
+
+    loop {
+        health.up();
+        let started = Instant::now();
+        let result = worker.run_once(ctx.clone()).await;
+        if ctx.is_cancelled() {
+            return;
+        }
+
+        let e = match result {
+            Ok(()) => {
+                log::info!("worker {} finished", worker.name);
+                health.remove();
+                return;
+            }
+            Err(e) => e,
+        };
+        if !options.restart_on_failure {
+            log::error!("worker {} failed: {e}", worker.name);
+            health.down(format!("failed: {e}"));
+            return;
+        }
+
+        failures = if started.elapsed() > options.max_backoff {
+            1
+        } else {
+            failures + 1
+        };
+        let delay = options.backoff(failures);
+        log::error!(
+            "worker {} failed, restarting in {delay:?}: {e}",
+            worker.name
+        );
+        health.down(format!("restarting in {delay:?} after: {e}"));
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = ctx.cancelled() => return,
+        }
+    }
+}
+
+/// Runs a job at every tick of its schedule until shutdown. Runs never
+/// overlap, ticks missed while the job is running are skipped
+async fn run_schedule(
+    job: ScheduledJob,
+    schedule: cron::Schedule,
+    ctx: WorkerContext,
+    health: WorkerHealth,
+) {
+    health.up();
+
+    while let Some(next) = schedule.upcoming(Utc).next() {
+        let delay = (next - Utc::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = ctx.cancelled() => return,
+        }
+
+        match (job.run)(ctx.clone()).await {
+            Ok(()) => health.up(),
+            Err(e) => {
+                log::error!("job {} failed: {e}", job.name);
+                health.down(format!("last run failed: {e}"));
+            }
+        }
+        if ctx.is_cancelled() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::health::HealthStatus;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn it_doubles_the_backoff_up_to_the_maximum() {
+        let options = WorkerOptions::default();
+
+        assert_eq!(options.backoff(1), Duration::from_secs(1));
+        assert_eq!(options.backoff(3), Duration::from_secs(4));
+        assert_eq!(options.backoff(40), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn it_accepts_five_and_six_field_cron_expressions() {
+        assert!(parse_cron("*/5 * * * *").is_ok());
+        assert!(parse_cron("0 */5 * * * *").is_ok());
+        assert!(parse_cron("every minute").is_err());
+    }
+
+    #[tokio::test]
+    async fn it_restarts_failing_workers_until_shutdown() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let worker = {
+            let runs = runs.clone();
+            Worker::new("consumer", move |_| {
+                let runs = runs.clone();
+                async move {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    Err(Error::InternalServerError("queue closed".to_string()))
+                }
+            })
+        };
+        let workers = Workers {
+            workers: vec![worker],
+            jobs: vec![],
+            options: WorkerOptions {
+                initial_backoff: Duration::from_millis(5),
+                report_readiness: true,
+                ..WorkerOptions::default()
+            },
+        };
+        let (health, shutdown) = (HealthIndicators::new(), ShutdownHandle::new());
+
+        let running = workers
+            .start(&NorthStateMap::new(), &health, &shutdown)
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        shutdown.trigger();
+        running.stop().await;
+
+        assert!(runs.load(Ordering::SeqCst) >= 2);
+        assert_eq!(health.status(), HealthStatus::Down);
+    }
+
+    #[tokio::test]
+    async fn it_restarts_panicking_workers_without_touching_readiness() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let worker = {
+            let runs = runs.clone();
+            Worker::new("consumer", move |_| {
+                let runs = runs.clone();
+                async move {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    panic!("poisoned message");
+                }
+            })
+        };
+        let workers = Workers {
+            workers: vec![worker],
+            jobs: vec![],
+            options: WorkerOptions {
+                initial_backoff: Duration::from_millis(5),
+                ..WorkerOptions::default()
+            },
+        };
+        let (health, shutdown) = (HealthIndicators::new(), ShutdownHandle::new());
+
+        let running = workers
+            .start(&NorthStateMap::new(), &health, &shutdown)
+            .unwrap();
+        // panics are slow to report, so wait for the restart rather than sleep
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while runs.load(Ordering::SeqCst) < 2 {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("the worker was not restarted");
+        shutdown.trigger();
+        running.stop().await;
+
+        assert_eq!(health.status(), HealthStatus::Up);
+        assert!(health.report().components.is_empty());
+    }
+}